@@ -0,0 +1,378 @@
+use super::error::{ConvertError, Result};
+use std::str::FromStr;
+
+/// D65 白点
+const WHITE_X: f32 = 0.950_47;
+const WHITE_Y: f32 = 1.0;
+const WHITE_Z: f32 = 1.088_83;
+
+/// 颜色空间
+///
+/// 各空间坐标都缩放到相近的量级（RGB 为 0-255，Lab/OkLab 的 L 为 0-100），
+/// 因此同一个距离阈值在不同空间下含义大致相当。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorSpace {
+    /// 未经变换的 sRGB 分量
+    Srgb,
+    /// 去除 gamma 的线性 RGB
+    LinearRgb,
+    /// CIE L*a*b*（D65）
+    #[default]
+    Lab,
+    /// OkLab
+    Oklab,
+}
+
+impl FromStr for ColorSpace {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "srgb" | "rgb" => Ok(ColorSpace::Srgb),
+            "linear-rgb" | "linear" => Ok(ColorSpace::LinearRgb),
+            "lab" | "cielab" => Ok(ColorSpace::Lab),
+            "oklab" => Ok(ColorSpace::Oklab),
+            _ => Err(ConvertError::InvalidOption(format!("未知的颜色空间: {}（可选: srgb, linear-rgb, lab, oklab）", s))),
+        }
+    }
+}
+
+impl ColorSpace {
+    /// 将 sRGB 颜色转换到当前空间
+    pub fn from_rgb(&self, rgb: [u8; 3]) -> [f32; 3] {
+        match self {
+            ColorSpace::Srgb => rgb.map(|c| c as f32),
+            ColorSpace::LinearRgb => rgb.map(|c| srgb_to_linear(c) * 255.0),
+            ColorSpace::Lab => rgb_to_lab(rgb),
+            ColorSpace::Oklab => {
+                let [l, a, b] = rgb_to_oklab(rgb);
+                [l * 100.0, a * 100.0, b * 100.0]
+            }
+        }
+    }
+
+    /// 将当前空间中的坐标转换回 sRGB（超出色域的分量会被截断）
+    pub fn to_rgb(&self, color: [f32; 3]) -> [u8; 3] {
+        match self {
+            ColorSpace::Srgb => color.map(clamp_u8),
+            ColorSpace::LinearRgb => color.map(|c| linear_to_srgb(c / 255.0)),
+            ColorSpace::Lab => lab_to_rgb(color),
+            ColorSpace::Oklab => oklab_to_rgb([color[0] / 100.0, color[1] / 100.0, color[2] / 100.0]),
+        }
+    }
+}
+
+/// 色差公式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeltaE {
+    /// ΔE76：所选空间中的欧几里得距离
+    Cie76,
+    /// CIEDE2000：只能与 CIELAB 一起使用
+    Ciede2000,
+}
+
+impl FromStr for DeltaE {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "76" | "cie76" | "de76" => Ok(DeltaE::Cie76),
+            "2000" | "ciede2000" | "de2000" => Ok(DeltaE::Ciede2000),
+            _ => Err(ConvertError::InvalidOption(format!("未知的色差公式: {}（可选: cie76, ciede2000）", s))),
+        }
+    }
+}
+
+/// 颜色距离度量，量化与图层分配共用
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ColorMetric {
+    /// 颜色空间
+    pub space: ColorSpace,
+    /// 色差公式；`None` 时在 CIELAB 中使用 CIEDE2000，在其他空间中使用 ΔE76
+    pub delta_e: Option<DeltaE>,
+}
+
+impl ColorMetric {
+    /// 实际使用的色差公式
+    pub fn delta_e(&self) -> DeltaE {
+        match (self.delta_e, self.space) {
+            (Some(delta_e), _) => delta_e,
+            (None, ColorSpace::Lab) => DeltaE::Ciede2000,
+            (None, _) => DeltaE::Cie76,
+        }
+    }
+
+    /// 检查颜色空间与色差公式的组合：CIEDE2000 只在 CIELAB 中有定义
+    pub fn validate(&self) -> Result<()> {
+        if self.delta_e() == DeltaE::Ciede2000 && self.space != ColorSpace::Lab {
+            return Err(ConvertError::InvalidOption(
+                "ciede2000只能与lab颜色空间一起使用（请改用cie76或不指定色差公式）".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// 实际计算距离所用的空间：CIEDE2000 始终在 CIELAB 中计算
+    pub fn working_space(&self) -> ColorSpace {
+        match self.delta_e() {
+            DeltaE::Cie76 => self.space,
+            DeltaE::Ciede2000 => ColorSpace::Lab,
+        }
+    }
+
+    /// 将 sRGB 颜色转换为工作空间坐标
+    pub fn to_working(&self, rgb: [u8; 3]) -> [f32; 3] {
+        self.working_space().from_rgb(rgb)
+    }
+
+    /// 将工作空间坐标转换回 sRGB
+    pub fn from_working(&self, color: [f32; 3]) -> [u8; 3] {
+        self.working_space().to_rgb(color)
+    }
+
+    /// 计算两个工作空间坐标之间的色差
+    pub fn distance(&self, a: [f32; 3], b: [f32; 3]) -> f32 {
+        match self.delta_e() {
+            DeltaE::Cie76 => {
+                ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+            }
+            DeltaE::Ciede2000 => ciede2000(a, b),
+        }
+    }
+
+    /// 计算两个 sRGB 颜色之间的色差
+    pub fn rgb_distance(&self, a: [u8; 3], b: [u8; 3]) -> f32 {
+        self.distance(self.to_working(a), self.to_working(b))
+    }
+}
+
+fn clamp_u8(c: f32) -> u8 {
+    c.round().clamp(0.0, 255.0) as u8
+}
+
+fn srgb_to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.0;
+    if c <= 0.040_45 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let s = if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    clamp_u8(s * 255.0)
+}
+
+fn rgb_to_xyz(rgb: [u8; 3]) -> [f32; 3] {
+    let [r, g, b] = rgb.map(srgb_to_linear);
+    [
+        0.412_456_4 * r + 0.357_576_1 * g + 0.180_437_5 * b,
+        0.212_672_9 * r + 0.715_152_2 * g + 0.072_175 * b,
+        0.019_333_9 * r + 0.119_192 * g + 0.950_304_1 * b,
+    ]
+}
+
+fn xyz_to_rgb(xyz: [f32; 3]) -> [u8; 3] {
+    let [x, y, z] = xyz;
+    [
+        linear_to_srgb(3.240_454_2 * x - 1.537_138_5 * y - 0.498_531_4 * z),
+        linear_to_srgb(-0.969_266 * x + 1.876_010_8 * y + 0.041_556 * z),
+        linear_to_srgb(0.055_643_4 * x - 0.204_025_9 * y + 1.057_225_2 * z),
+    ]
+}
+
+fn lab_f(t: f32) -> f32 {
+    const DELTA: f32 = 6.0 / 29.0;
+    if t > DELTA.powi(3) {
+        t.cbrt()
+    } else {
+        t / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+    }
+}
+
+fn lab_f_inv(t: f32) -> f32 {
+    const DELTA: f32 = 6.0 / 29.0;
+    if t > DELTA {
+        t.powi(3)
+    } else {
+        3.0 * DELTA * DELTA * (t - 4.0 / 29.0)
+    }
+}
+
+fn rgb_to_lab(rgb: [u8; 3]) -> [f32; 3] {
+    let [x, y, z] = rgb_to_xyz(rgb);
+    let fx = lab_f(x / WHITE_X);
+    let fy = lab_f(y / WHITE_Y);
+    let fz = lab_f(z / WHITE_Z);
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+fn lab_to_rgb(lab: [f32; 3]) -> [u8; 3] {
+    let fy = (lab[0] + 16.0) / 116.0;
+    let fx = fy + lab[1] / 500.0;
+    let fz = fy - lab[2] / 200.0;
+    xyz_to_rgb([lab_f_inv(fx) * WHITE_X, lab_f_inv(fy) * WHITE_Y, lab_f_inv(fz) * WHITE_Z])
+}
+
+fn rgb_to_oklab(rgb: [u8; 3]) -> [f32; 3] {
+    let [r, g, b] = rgb.map(srgb_to_linear);
+    let l = (0.412_221_46 * r + 0.536_332_55 * g + 0.051_445_995 * b).cbrt();
+    let m = (0.211_903_5 * r + 0.680_699_5 * g + 0.107_396_96 * b).cbrt();
+    let s = (0.088_302_46 * r + 0.281_718_85 * g + 0.629_978_7 * b).cbrt();
+    [
+        0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+        1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+        0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+    ]
+}
+
+fn oklab_to_rgb(lab: [f32; 3]) -> [u8; 3] {
+    let [l, a, b] = lab;
+    let l_ = (l + 0.396_337_78 * a + 0.215_803_76 * b).powi(3);
+    let m_ = (l - 0.105_561_346 * a - 0.063_854_17 * b).powi(3);
+    let s_ = (l - 0.089_484_18 * a - 1.291_485_5 * b).powi(3);
+    [
+        linear_to_srgb(4.076_741_7 * l_ - 3.307_711_6 * m_ + 0.230_969_94 * s_),
+        linear_to_srgb(-1.268_438 * l_ + 2.609_757_4 * m_ - 0.341_319_38 * s_),
+        linear_to_srgb(-0.004_196_086_3 * l_ - 0.703_418_6 * m_ + 1.707_614_7 * s_),
+    ]
+}
+
+/// CIEDE2000 色差（kL = kC = kH = 1）
+fn ciede2000(lab1: [f32; 3], lab2: [f32; 3]) -> f32 {
+    let [l1, a1, b1] = lab1.map(|v| v as f64);
+    let [l2, a2, b2] = lab2.map(|v| v as f64);
+
+    let c1 = (a1 * a1 + b1 * b1).sqrt();
+    let c2 = (a2 * a2 + b2 * b2).sqrt();
+    let c_bar7 = ((c1 + c2) / 2.0).powi(7);
+    let g = 0.5 * (1.0 - (c_bar7 / (c_bar7 + 25f64.powi(7))).sqrt());
+
+    let a1p = (1.0 + g) * a1;
+    let a2p = (1.0 + g) * a2;
+    let c1p = (a1p * a1p + b1 * b1).sqrt();
+    let c2p = (a2p * a2p + b2 * b2).sqrt();
+
+    let hue = |b: f64, a: f64| {
+        if a == 0.0 && b == 0.0 {
+            0.0
+        } else {
+            let h = b.atan2(a).to_degrees();
+            if h < 0.0 { h + 360.0 } else { h }
+        }
+    };
+    let h1p = hue(b1, a1p);
+    let h2p = hue(b2, a2p);
+
+    let dl = l2 - l1;
+    let dc = c2p - c1p;
+    let dh = if c1p * c2p == 0.0 {
+        0.0
+    } else if (h2p - h1p).abs() <= 180.0 {
+        h2p - h1p
+    } else if h2p - h1p > 180.0 {
+        h2p - h1p - 360.0
+    } else {
+        h2p - h1p + 360.0
+    };
+    let dh_big = 2.0 * (c1p * c2p).sqrt() * (dh.to_radians() / 2.0).sin();
+
+    let l_bar = (l1 + l2) / 2.0;
+    let c_bar_p = (c1p + c2p) / 2.0;
+    let h_bar_p = if c1p * c2p == 0.0 {
+        h1p + h2p
+    } else if (h1p - h2p).abs() <= 180.0 {
+        (h1p + h2p) / 2.0
+    } else if h1p + h2p < 360.0 {
+        (h1p + h2p + 360.0) / 2.0
+    } else {
+        (h1p + h2p - 360.0) / 2.0
+    };
+
+    let t = 1.0 - 0.17 * (h_bar_p - 30.0).to_radians().cos()
+        + 0.24 * (2.0 * h_bar_p).to_radians().cos()
+        + 0.32 * (3.0 * h_bar_p + 6.0).to_radians().cos()
+        - 0.20 * (4.0 * h_bar_p - 63.0).to_radians().cos();
+    let d_theta = 30.0 * (-((h_bar_p - 275.0) / 25.0).powi(2)).exp();
+    let c_bar_p7 = c_bar_p.powi(7);
+    let r_c = 2.0 * (c_bar_p7 / (c_bar_p7 + 25f64.powi(7))).sqrt();
+    let l_term = (l_bar - 50.0).powi(2);
+    let s_l = 1.0 + 0.015 * l_term / (20.0 + l_term).sqrt();
+    let s_c = 1.0 + 0.045 * c_bar_p;
+    let s_h = 1.0 + 0.015 * c_bar_p * t;
+    let r_t = -(2.0 * d_theta).to_radians().sin() * r_c;
+
+    let dl_term = dl / s_l;
+    let dc_term = dc / s_c;
+    let dh_term = dh_big / s_h;
+    (dl_term * dl_term + dc_term * dc_term + dh_term * dh_term + r_t * dc_term * dh_term).sqrt() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sharma, Wu, Dalal (2005) 给出的CIEDE2000测试数据：(L1, a1, b1), (L2, a2, b2), ΔE00
+    const SHARMA_PAIRS: [([f32; 3], [f32; 3], f32); 34] = [
+        ([50.0, 2.6772, -79.7751], [50.0, 0.0, -82.7485], 2.0425),
+        ([50.0, 3.1571, -77.2803], [50.0, 0.0, -82.7485], 2.8615),
+        ([50.0, 2.8361, -74.0200], [50.0, 0.0, -82.7485], 3.4412),
+        ([50.0, -1.3802, -84.2814], [50.0, 0.0, -82.7485], 1.0000),
+        ([50.0, -1.1848, -84.8006], [50.0, 0.0, -82.7485], 1.0000),
+        ([50.0, -0.9009, -85.5211], [50.0, 0.0, -82.7485], 1.0000),
+        ([50.0, 0.0, 0.0], [50.0, -1.0, 2.0], 2.3669),
+        ([50.0, -1.0, 2.0], [50.0, 0.0, 0.0], 2.3669),
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0009], 7.1792),
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0010], 7.1792),
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0011], 7.2195),
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0012], 7.2195),
+        ([50.0, -0.0010, 2.4900], [50.0, 0.0009, -2.4900], 4.8045),
+        ([50.0, -0.0010, 2.4900], [50.0, 0.0010, -2.4900], 4.8045),
+        ([50.0, -0.0010, 2.4900], [50.0, 0.0011, -2.4900], 4.7461),
+        ([50.0, 2.5000, 0.0], [50.0, 0.0, -2.5000], 4.3065),
+        ([50.0, 2.5000, 0.0], [73.0, 25.0, -18.0], 27.1492),
+        ([50.0, 2.5000, 0.0], [61.0, -5.0, 29.0], 22.8977),
+        ([50.0, 2.5000, 0.0], [56.0, -27.0, -3.0], 31.9030),
+        ([50.0, 2.5000, 0.0], [58.0, 24.0, 15.0], 19.4535),
+        ([50.0, 2.5000, 0.0], [50.0, 3.1736, 0.5854], 1.0000),
+        ([50.0, 2.5000, 0.0], [50.0, 3.2972, 0.0], 1.0000),
+        ([50.0, 2.5000, 0.0], [50.0, 1.8634, 0.5757], 1.0000),
+        ([50.0, 2.5000, 0.0], [50.0, 3.2592, 0.3350], 1.0000),
+        ([60.2574, -34.0099, 36.2677], [60.4626, -34.1751, 39.4387], 1.2644),
+        ([63.0109, -31.0961, -5.8663], [62.8187, -29.7946, -4.0864], 1.2630),
+        ([61.2901, 3.7196, -5.3901], [61.4292, 2.2480, -4.9620], 1.8731),
+        ([35.0831, -44.1164, 3.7933], [35.0232, -40.0716, 1.5901], 1.8645),
+        ([22.7233, 20.0904, -46.6940], [23.0331, 14.9730, -42.5619], 2.0373),
+        ([36.4612, 47.8580, 18.3852], [36.2715, 50.5065, 21.2231], 1.4146),
+        ([90.8027, -2.0831, 1.4410], [91.1528, -1.6435, 0.0447], 1.4441),
+        ([90.9257, -0.5406, -0.9208], [88.6381, -0.8985, -0.7239], 1.5381),
+        ([6.7747, -0.2908, -2.4247], [5.8714, -0.0985, -2.2286], 0.6377),
+        ([2.0776, 0.0795, -1.1350], [0.9033, -0.0636, -0.5514], 0.9082),
+    ];
+
+    #[test]
+    fn ciede2000_matches_sharma_reference_pairs() {
+        for (i, &(lab1, lab2, expected)) in SHARMA_PAIRS.iter().enumerate() {
+            let forward = ciede2000(lab1, lab2);
+            let backward = ciede2000(lab2, lab1);
+            assert!((forward - expected).abs() < 1e-3, "第{}组: {} ≠ {}", i + 1, forward, expected);
+            assert!((backward - expected).abs() < 1e-3, "第{}组（交换）: {} ≠ {}", i + 1, backward, expected);
+        }
+    }
+
+    #[test]
+    fn lab_metric_uses_ciede2000_by_default() {
+        let metric = ColorMetric { space: ColorSpace::Lab, delta_e: None };
+        let (lab1, lab2, expected) = SHARMA_PAIRS[16];
+        assert!((metric.distance(lab1, lab2) - expected).abs() < 1e-3);
+
+        let srgb = ColorMetric { space: ColorSpace::Srgb, delta_e: Some(DeltaE::Ciede2000) };
+        assert!(srgb.validate().is_err());
+    }
+}
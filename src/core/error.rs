@@ -0,0 +1,125 @@
+use std::fmt;
+use std::io;
+
+use super::limits::LimitError;
+
+/// 转换过程中的错误
+///
+/// 每个变体有稳定的错误码（`code`）与命令行退出码（`exit_code`），
+/// 供调用方区分错误类型；显示的消息只用于提示，不保证稳定。
+#[derive(Debug)]
+pub enum ConvertError {
+    /// 选项无效：未知的枚举值、超出范围的参数等
+    InvalidOption(String),
+    /// SVG无法解析
+    ParseSvg(String),
+    /// 尺寸无效：输出宽高为0或过大、缩放倍数不为正数、图标尺寸不受支持等
+    InvalidDimensions(String),
+    /// 不支持或无法识别的图像格式
+    UnsupportedFormat(String),
+    /// 图像解码失败
+    Decode(String),
+    /// 图像编码失败
+    Encode(String),
+    /// 找不到要渲染的元素
+    ElementNotFound(String),
+    /// 字体无法加载
+    Font(String),
+    /// 文件或流读写失败，`context` 说明正在进行的操作
+    Io { context: String, source: io::Error },
+    /// 超出安全模式的资源上限
+    LimitExceeded(LimitError),
+}
+
+/// 核心模块使用的结果类型
+pub type Result<T> = std::result::Result<T, ConvertError>;
+
+impl ConvertError {
+    /// 稳定的错误码
+    pub fn code(&self) -> &'static str {
+        match self {
+            ConvertError::InvalidOption(_) => "invalid_option",
+            ConvertError::ParseSvg(_) => "parse_svg",
+            ConvertError::InvalidDimensions(_) => "invalid_dimensions",
+            ConvertError::UnsupportedFormat(_) => "unsupported_format",
+            ConvertError::Decode(_) => "decode",
+            ConvertError::Encode(_) => "encode",
+            ConvertError::ElementNotFound(_) => "element_not_found",
+            ConvertError::Font(_) => "font",
+            ConvertError::Io { .. } => "io",
+            ConvertError::LimitExceeded(_) => "limit_exceeded",
+        }
+    }
+
+    /// 命令行工具的退出码，与命令行参数错误（2）一致的选项错误也返回2
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            ConvertError::InvalidOption(_) => 2,
+            ConvertError::ParseSvg(_) => 3,
+            ConvertError::InvalidDimensions(_) => 4,
+            ConvertError::UnsupportedFormat(_) => 5,
+            ConvertError::Decode(_) => 6,
+            ConvertError::Encode(_) => 7,
+            ConvertError::ElementNotFound(_) => 8,
+            ConvertError::Font(_) => 9,
+            ConvertError::Io { .. } => 10,
+            ConvertError::LimitExceeded(_) => 11,
+        }
+    }
+
+    /// 为读写错误附加说明，如 `无法读取 input.svg`
+    pub fn io(context: impl Into<String>, source: io::Error) -> Self {
+        ConvertError::Io { context: context.into(), source }
+    }
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConvertError::InvalidOption(message)
+            | ConvertError::ParseSvg(message)
+            | ConvertError::InvalidDimensions(message)
+            | ConvertError::UnsupportedFormat(message)
+            | ConvertError::Decode(message)
+            | ConvertError::Encode(message)
+            | ConvertError::ElementNotFound(message)
+            | ConvertError::Font(message) => f.write_str(message),
+            ConvertError::Io { context, source } => write!(f, "{}: {}", context, source),
+            ConvertError::LimitExceeded(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for ConvertError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConvertError::Io { source, .. } => Some(source),
+            ConvertError::LimitExceeded(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ConvertError {
+    fn from(error: io::Error) -> Self {
+        ConvertError::io("读写错误", error)
+    }
+}
+
+impl From<LimitError> for ConvertError {
+    fn from(error: LimitError) -> Self {
+        ConvertError::LimitExceeded(error)
+    }
+}
+
+impl From<roxmltree::Error> for ConvertError {
+    fn from(error: roxmltree::Error) -> Self {
+        ConvertError::ParseSvg(format!("SVG解析错误: {}", error))
+    }
+}
+
+impl From<resvg::usvg::Error> for ConvertError {
+    fn from(error: resvg::usvg::Error) -> Self {
+        ConvertError::ParseSvg(format!("SVG解析错误: {}", error))
+    }
+}
@@ -0,0 +1,362 @@
+use kurbo::{CubicBez, ParamCurve, ParamCurveDeriv, Point, Vec2};
+
+use super::path::{Segment, SubPath};
+
+/// 估计切线与检测拐角时向两侧取样的点数
+const CORNER_WINDOW: usize = 4;
+
+/// 单段三次曲线允许的最大递归切分深度
+const MAX_SPLIT_DEPTH: usize = 32;
+
+/// Newton 重参数化的最大迭代次数
+const MAX_REPARAMETERIZE: usize = 4;
+
+/// 将闭合折线拟合为平滑的贝塞尔子路径（Schneider 算法）
+///
+/// 方向改变超过 `corner_angle` 度的位置保留为尖角，其余位置切线连续。
+/// `tolerance` 为拟合曲线与原始轮廓之间允许的最大距离（像素）。
+/// 控制点接近弦线的曲线段输出为直线，可由二次曲线精确表示的输出为二次曲线。
+pub fn fit_contour(points: &[(f32, f32)], tolerance: f64, corner_angle: f64) -> SubPath {
+    let vertices = densify(points);
+    let n = vertices.len();
+
+    // 太小的轮廓没有可拟合的曲线，直接输出多边形
+    if n < 2 * CORNER_WINDOW + 1 {
+        return SubPath::polygon(points);
+    }
+
+    // 以各小段的中点作为拟合数据，消除像素阶梯带来的锯齿
+    let midpoints: Vec<Point> = (0..n).map(|i| vertices[i].midpoint(vertices[(i + 1) % n])).collect();
+
+    let corners = detect_corners(&vertices, corner_angle);
+    // 没有拐角时（如圆形）在两个平滑连接点处断开
+    let (breaks, smooth) = if corners.is_empty() {
+        (vec![0, n / 2], true)
+    } else {
+        (corners, false)
+    };
+
+    let mut cubics = Vec::new();
+    for (j, &start) in breaks.iter().enumerate() {
+        let end = breaks[(j + 1) % breaks.len()];
+        let len = if end > start { end - start } else { end + n - start };
+
+        // 拐角处使用原始顶点，平滑连接处使用中点
+        let segment: Vec<Point> = if smooth {
+            (0..=len).map(|i| midpoints[(start + i) % n]).collect()
+        } else {
+            std::iter::once(vertices[start])
+                .chain((0..len).map(|i| midpoints[(start + i) % n]))
+                .chain(std::iter::once(vertices[end]))
+                .collect()
+        };
+
+        let (t1, t2) = if smooth {
+            (smooth_tangent(&midpoints, start), -smooth_tangent(&midpoints, end))
+        } else {
+            end_tangents(&segment)
+        };
+        fit_cubic(&segment, t1, t2, tolerance, 0, &mut cubics);
+    }
+
+    SubPath {
+        start: if smooth { midpoints[breaks[0]] } else { vertices[breaks[0]] },
+        segments: cubics.into_iter()
+            .map(|cubic| simplest_segment(cubic, tolerance * 0.1))
+            .collect(),
+        closed: true,
+    }
+}
+
+/// 将折线按不超过1像素的步长加密
+fn densify(points: &[(f32, f32)]) -> Vec<Point> {
+    let mut dense = Vec::new();
+    for i in 0..points.len() {
+        let a = Point::new(points[i].0 as f64, points[i].1 as f64);
+        let next = points[(i + 1) % points.len()];
+        let b = Point::new(next.0 as f64, next.1 as f64);
+        let steps = (b - a).hypot().ceil().max(1.0) as usize;
+        for s in 0..steps {
+            dense.push(a.lerp(b, s as f64 / steps as f64));
+        }
+    }
+    dense
+}
+
+/// 检测拐角：比较前后各 `CORNER_WINDOW` 个点的走向，并做非极大值抑制
+fn detect_corners(points: &[Point], corner_angle: f64) -> Vec<usize> {
+    let n = points.len();
+    let k = CORNER_WINDOW;
+    let turns: Vec<f64> = (0..n)
+        .map(|i| {
+            let before = points[i] - points[(i + n - k) % n];
+            let after = points[(i + k) % n] - points[i];
+            before.cross(after).atan2(before.dot(after)).abs().to_degrees()
+        })
+        .collect();
+
+    (0..n)
+        .filter(|&i| turns[i] > corner_angle)
+        .filter(|&i| {
+            (1..=k).all(|d| {
+                let prev = turns[(i + n - d) % n];
+                let next = turns[(i + d) % n];
+                turns[i] > prev && turns[i] >= next
+            })
+        })
+        .collect()
+}
+
+/// 平滑连接点处的切线方向
+fn smooth_tangent(points: &[Point], i: usize) -> Vec2 {
+    let n = points.len();
+    let k = CORNER_WINDOW.min(n / 2);
+    (points[(i + k) % n] - points[(i + n - k) % n]).normalize()
+}
+
+/// 拐角之间一段的两端切线，分别指向曲线内部
+fn end_tangents(segment: &[Point]) -> (Vec2, Vec2) {
+    let last = segment.len() - 1;
+    let k = CORNER_WINDOW.min(last);
+    (
+        (segment[k] - segment[0]).normalize(),
+        (segment[last - k] - segment[last]).normalize(),
+    )
+}
+
+/// 递归拟合三次贝塞尔曲线
+fn fit_cubic(points: &[Point], t1: Vec2, t2: Vec2, tolerance: f64, depth: usize, out: &mut Vec<CubicBez>) {
+    let first = points[0];
+    let last = points[points.len() - 1];
+
+    if points.len() == 2 {
+        let d = (last - first).hypot() / 3.0;
+        out.push(CubicBez::new(first, first + t1 * d, last + t2 * d, last));
+        return;
+    }
+
+    let mut u = chord_length_parameterize(points);
+    let mut cubic = generate_bezier(points, &u, t1, t2);
+    let (mut error, mut split) = max_error(points, &cubic, &u);
+    let tolerance_sq = tolerance * tolerance;
+
+    if error < tolerance_sq {
+        out.push(cubic);
+        return;
+    }
+
+    // 误差不太大时尝试用 Newton 迭代改进参数
+    if error < tolerance_sq * 16.0 {
+        for _ in 0..MAX_REPARAMETERIZE {
+            u = reparameterize(points, &u, &cubic);
+            cubic = generate_bezier(points, &u, t1, t2);
+            (error, split) = max_error(points, &cubic, &u);
+            if error < tolerance_sq {
+                out.push(cubic);
+                return;
+            }
+        }
+    }
+
+    if depth >= MAX_SPLIT_DEPTH {
+        out.push(cubic);
+        return;
+    }
+
+    // 在误差最大处切分，切分点两侧保持切线连续
+    let k = CORNER_WINDOW.min(split).min(points.len() - 1 - split);
+    let center = (points[split - k] - points[split + k]).normalize();
+    fit_cubic(&points[..=split], t1, center, tolerance, depth + 1, out);
+    fit_cubic(&points[split..], -center, t2, tolerance, depth + 1, out);
+}
+
+/// 按弦长为每个点分配参数
+fn chord_length_parameterize(points: &[Point]) -> Vec<f64> {
+    let mut u = Vec::with_capacity(points.len());
+    let mut total = 0.0;
+    u.push(0.0);
+    for pair in points.windows(2) {
+        total += (pair[1] - pair[0]).hypot();
+        u.push(total);
+    }
+    if total > 0.0 {
+        for value in &mut u {
+            *value /= total;
+        }
+    }
+    u
+}
+
+/// 在端点与切线方向固定的前提下，最小二乘求解控制点距离
+fn generate_bezier(points: &[Point], u: &[f64], t1: Vec2, t2: Vec2) -> CubicBez {
+    let first = points[0];
+    let last = points[points.len() - 1];
+
+    let mut c = [[0.0; 2]; 2];
+    let mut x = [0.0; 2];
+    for (&p, &t) in points.iter().zip(u) {
+        let mt = 1.0 - t;
+        let b0 = mt * mt * mt;
+        let b1 = 3.0 * t * mt * mt;
+        let b2 = 3.0 * t * t * mt;
+        let b3 = t * t * t;
+        let a1 = t1 * b1;
+        let a2 = t2 * b2;
+        c[0][0] += a1.dot(a1);
+        c[0][1] += a1.dot(a2);
+        c[1][1] += a2.dot(a2);
+        let tmp = p.to_vec2() - (first.to_vec2() * (b0 + b1) + last.to_vec2() * (b2 + b3));
+        x[0] += a1.dot(tmp);
+        x[1] += a2.dot(tmp);
+    }
+    c[1][0] = c[0][1];
+
+    let det = c[0][0] * c[1][1] - c[0][1] * c[1][0];
+    let (alpha1, alpha2) = if det.abs() > f64::EPSILON {
+        (
+            (x[0] * c[1][1] - x[1] * c[0][1]) / det,
+            (c[0][0] * x[1] - c[1][0] * x[0]) / det,
+        )
+    } else {
+        (0.0, 0.0)
+    };
+
+    // 解不合理时退化为弦长的三分之一（Wu/Barsky 启发式）
+    let chord = (last - first).hypot();
+    let epsilon = 1e-6 * chord;
+    if alpha1 < epsilon || alpha2 < epsilon {
+        let d = chord / 3.0;
+        return CubicBez::new(first, first + t1 * d, last + t2 * d, last);
+    }
+
+    CubicBez::new(first, first + t1 * alpha1, last + t2 * alpha2, last)
+}
+
+/// 返回最大距离平方及其所在下标
+fn max_error(points: &[Point], cubic: &CubicBez, u: &[f64]) -> (f64, usize) {
+    let mut max = 0.0;
+    let mut split = points.len() / 2;
+    for i in 1..points.len() - 1 {
+        let d = (cubic.eval(u[i]) - points[i]).hypot2();
+        if d >= max {
+            max = d;
+            split = i;
+        }
+    }
+    (max, split)
+}
+
+/// Newton–Raphson 改进每个点对应的曲线参数
+fn reparameterize(points: &[Point], u: &[f64], cubic: &CubicBez) -> Vec<f64> {
+    let d1 = cubic.deriv();
+    let d2 = d1.deriv();
+    points.iter()
+        .zip(u)
+        .map(|(&p, &t)| {
+            let diff = cubic.eval(t) - p;
+            let q1 = d1.eval(t).to_vec2();
+            let q2 = d2.eval(t).to_vec2();
+            let denominator = q1.dot(q1) + diff.dot(q2);
+            if denominator.abs() < f64::EPSILON {
+                t
+            } else {
+                (t - diff.dot(q1) / denominator).clamp(0.0, 1.0)
+            }
+        })
+        .collect()
+}
+
+/// 将三次曲线转换为最简单的等价路径段
+fn simplest_segment(cubic: CubicBez, epsilon: f64) -> Segment {
+    let CubicBez { p0, p1, p2, p3 } = cubic;
+
+    if distance_to_line(p1, p0, p3) < epsilon && distance_to_line(p2, p0, p3) < epsilon {
+        return Segment::Line(p3);
+    }
+
+    // 由升阶关系反推二次曲线控制点，两端求得的结果一致时即为二次曲线
+    let q1 = p0 + (p1 - p0) * 1.5;
+    let q2 = p3 + (p2 - p3) * 1.5;
+    if (q1 - q2).hypot() < epsilon {
+        Segment::Quad(q1.midpoint(q2), p3)
+    } else {
+        Segment::Cubic(p1, p2, p3)
+    }
+}
+
+fn distance_to_line(p: Point, a: Point, b: Point) -> f64 {
+    let ab = b - a;
+    let length = ab.hypot();
+    if length < f64::EPSILON {
+        (p - a).hypot()
+    } else {
+        ab.cross(p - a).abs() / length
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 以 (cx, cy) 为圆心、半径为 `r` 的正 `n` 边形
+    fn circle(cx: f32, cy: f32, r: f32, n: usize) -> Vec<(f32, f32)> {
+        (0..n)
+            .map(|i| {
+                let angle = i as f32 / n as f32 * std::f32::consts::TAU;
+                (cx + r * angle.cos(), cy + r * angle.sin())
+            })
+            .collect()
+    }
+
+    /// 沿子路径每段均匀取样
+    fn samples(sub: &SubPath) -> Vec<Point> {
+        sub.to_bezpath()
+            .segments()
+            .flat_map(|seg| (0..=16).map(move |i| seg.eval(i as f64 / 16.0)))
+            .collect()
+    }
+
+    #[test]
+    fn circle_fit_stays_within_tolerance() {
+        let tolerance = 0.5;
+        let sub = fit_contour(&circle(30.0, 30.0, 20.0, 64), tolerance, 60.0);
+        assert!(sub.closed);
+        assert!(sub.segments.len() < 16, "圆应拟合为少量曲线，实际{}段", sub.segments.len());
+        assert!(sub.segments.iter().all(|seg| !matches!(seg, Segment::Line(_))));
+        for p in samples(&sub) {
+            let error = ((p - Point::new(30.0, 30.0)).hypot() - 20.0).abs();
+            // 正64边形与圆之间最多相差约0.03像素
+            assert!(error <= tolerance + 0.05, "{:?} 距圆 {}", p, error);
+        }
+    }
+
+    #[test]
+    fn square_corners_are_kept() {
+        let square = [(0.0, 0.0), (20.0, 0.0), (20.0, 20.0), (0.0, 20.0)];
+        let sub = fit_contour(&square, 0.5, 60.0);
+        let vertices = sub.vertices();
+        for &(x, y) in &square {
+            let corner = Point::new(x as f64, y as f64);
+            assert!(vertices.iter().any(|v| (*v - corner).hypot() < 1e-6), "缺少拐角 {:?}: {:?}", corner, vertices);
+        }
+        // 直边输出为直线段
+        assert!(sub.segments.iter().all(|seg| matches!(seg, Segment::Line(_))), "{:?}", sub.segments);
+        assert!((sub.area() - 400.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn rounded_corners_below_the_angle_stay_smooth() {
+        // 八边形每个顶点只转45°，低于60°的拐角阈值时整体按平滑曲线拟合
+        let octagon = circle(20.0, 20.0, 10.0, 8);
+        let smooth = fit_contour(&octagon, 0.5, 60.0);
+        let sharp = fit_contour(&octagon, 0.5, 30.0);
+        let corners = |sub: &SubPath| {
+            let vertices = sub.vertices();
+            octagon.iter()
+                .filter(|&&(x, y)| vertices.iter().any(|v| (*v - Point::new(x as f64, y as f64)).hypot() < 1e-3))
+                .count()
+        };
+        assert_eq!(corners(&smooth), 0);
+        assert_eq!(corners(&sharp), 8);
+    }
+}
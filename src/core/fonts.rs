@@ -0,0 +1,147 @@
+use super::error::{ConvertError, Result};
+use resvg::usvg::{self, fontdb};
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+
+/// 未指定 `font-family` 时使用的字体族
+const DEFAULT_FONT_FAMILY: &str = "sans-serif";
+
+/// CSS通用字体族，以及系统中缺少默认映射时按名称优先挑选的字体
+const GENERIC_FAMILIES: [(&str, &str); 5] = [
+    ("serif", "Serif"),
+    ("sans-serif", "Sans"),
+    ("monospace", "Mono"),
+    ("cursive", "Script"),
+    ("fantasy", "Display"),
+];
+
+/// 系统字体只扫描一次，之后复用
+static SYSTEM_FONTS: OnceLock<fontdb::Database> = OnceLock::new();
+
+/// 渲染 `<text>` 所用的字体来源
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontOptions {
+    /// 加载系统字体（WebAssembly中没有系统字体）
+    pub system_fonts: bool,
+    /// 额外加载的字体文件或目录，目录会被递归扫描
+    pub paths: Vec<PathBuf>,
+    /// 内存中的字体文件数据（TTF、OTF或TTC）
+    pub data: Vec<Arc<Vec<u8>>>,
+    /// 未指定 `font-family` 时使用的字体族
+    pub default_family: String,
+}
+
+impl Default for FontOptions {
+    fn default() -> Self {
+        FontOptions {
+            system_fonts: !cfg!(target_arch = "wasm32"),
+            paths: Vec::new(),
+            data: Vec::new(),
+            default_family: DEFAULT_FONT_FAMILY.to_string(),
+        }
+    }
+}
+
+/// 按选项构建字体数据库
+///
+/// 通用字体族（如 `sans-serif`）默认映射的字体不存在时，改为映射到已加载的字体，
+/// 使没有匹配字体的文本回退到可用字体，而不是不显示。
+pub fn load_fonts(options: &FontOptions) -> Result<fontdb::Database> {
+    let mut db = if options.system_fonts {
+        SYSTEM_FONTS
+            .get_or_init(|| {
+                let mut db = fontdb::Database::new();
+                db.load_system_fonts();
+                db
+            })
+            .clone()
+    } else {
+        fontdb::Database::new()
+    };
+
+    for path in &options.paths {
+        if path.is_dir() {
+            db.load_fonts_dir(path);
+        } else if path.is_file() {
+            db.load_font_file(path)
+                .map_err(|e| ConvertError::Font(format!("无法加载字体文件 {}: {}", path.display(), e)))?;
+        } else {
+            return Err(ConvertError::Font(format!("字体路径不存在: {}", path.display())));
+        }
+    }
+    for data in &options.data {
+        let ids = db.load_font_source(fontdb::Source::Binary(data.clone()));
+        if ids.is_empty() {
+            return Err(ConvertError::Font("无法识别的字体数据（支持TTF、OTF、TTC）".to_string()));
+        }
+    }
+
+    resolve_generic_families(&mut db);
+    Ok(db)
+}
+
+/// 为找不到对应字体的通用字体族挑选一个已加载的字体
+fn resolve_generic_families(db: &mut fontdb::Database) {
+    let family_names: Vec<String> = db.faces()
+        .filter_map(|face| face.families.first().map(|(name, _)| name.clone()))
+        .collect();
+    let Some(fallback) = family_names.first().cloned() else { return };
+
+    for (generic, hint) in GENERIC_FAMILIES {
+        let family = generic_family(generic);
+        if find_family(db, family).is_some() {
+            continue;
+        }
+        let name = family_names.iter()
+            .find(|name| name.contains(hint) && !(hint == "Sans" && name.contains("Mono")))
+            .cloned()
+            .unwrap_or_else(|| fallback.clone());
+        match generic {
+            "serif" => db.set_serif_family(name),
+            "sans-serif" => db.set_sans_serif_family(name),
+            "monospace" => db.set_monospace_family(name),
+            "cursive" => db.set_cursive_family(name),
+            _ => db.set_fantasy_family(name),
+        }
+    }
+}
+
+/// 将 `font-family` 中的名称转换为字体数据库的查询条件
+fn generic_family(name: &str) -> fontdb::Family<'_> {
+    match name {
+        "serif" => fontdb::Family::Serif,
+        "sans-serif" => fontdb::Family::SansSerif,
+        "monospace" => fontdb::Family::Monospace,
+        "cursive" => fontdb::Family::Cursive,
+        "fantasy" => fontdb::Family::Fantasy,
+        _ => fontdb::Family::Name(name),
+    }
+}
+
+fn find_family(db: &fontdb::Database, family: fontdb::Family) -> Option<fontdb::ID> {
+    db.query(&fontdb::Query {
+        families: &[family],
+        ..Default::default()
+    })
+}
+
+/// 列出文本中引用但字体数据库中不存在的字体族，按名称排序去重
+///
+/// 这些文本会回退到同一 `font-family` 列表中的其他字体或默认字体。
+pub fn missing_families(tree: &usvg::Tree, db: &fontdb::Database) -> Vec<String> {
+    let mut missing = Vec::new();
+    for node in tree.root.descendants() {
+        if let usvg::NodeKind::Text(ref text) = *node.borrow() {
+            for span in text.chunks.iter().flat_map(|chunk| &chunk.spans) {
+                for name in &span.font.families {
+                    if find_family(db, generic_family(name)).is_none() {
+                        missing.push(name.clone());
+                    }
+                }
+            }
+        }
+    }
+    missing.sort();
+    missing.dedup();
+    missing
+}
@@ -0,0 +1,206 @@
+use super::error::{ConvertError, Result};
+use image::codecs::ico::{IcoEncoder, IcoFrame};
+use image::ColorType;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::svg2png::{self, FileReport, OutputFormat, PngColor, PngOptions, RenderOptions};
+
+/// favicon.ico 及Windows应用图标的标准尺寸
+pub const ICO_SIZES: [u32; 6] = [16, 32, 48, 64, 128, 256];
+
+/// macOS应用图标的默认尺寸（ICNS没有48像素的PNG图标类型）
+pub const ICNS_SIZES: [u32; 5] = [16, 32, 64, 128, 256];
+
+/// 图标文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IconFormat {
+    /// Windows ICO，每个尺寸以PNG形式存储，边长不超过256
+    #[default]
+    Ico,
+    /// Apple ICNS，每个尺寸以PNG形式存储
+    Icns,
+}
+
+impl FromStr for IconFormat {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "ico" => Ok(IconFormat::Ico),
+            "icns" => Ok(IconFormat::Icns),
+            _ => Err(ConvertError::InvalidOption(format!("未知的图标格式: {}（可选: ico, icns）", s))),
+        }
+    }
+}
+
+impl IconFormat {
+    /// 根据文件扩展名推断图标格式
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?;
+        extension.parse().ok()
+    }
+
+    /// 该格式的默认尺寸集合
+    pub fn default_sizes(self) -> &'static [u32] {
+        match self {
+            IconFormat::Ico => &ICO_SIZES,
+            IconFormat::Icns => &ICNS_SIZES,
+        }
+    }
+}
+
+/// 将SVG按各个尺寸渲染为正方形PNG并打包为图标文件数据
+///
+/// SVG只解析一次；`sizes` 为空时使用格式的默认尺寸。宽高比不为1的SVG按 `options.fit` 适配，
+/// `options` 中的宽高、缩放与输出格式设置被忽略。
+pub fn convert_svg_to_icon(
+    svg_data: &str,
+    format: IconFormat,
+    sizes: &[u32],
+    options: &RenderOptions,
+) -> Result<Vec<u8>> {
+    let tree = svg2png::parse_input(svg_data, options)?;
+    pack_tree(&tree, format, sizes, options)
+}
+
+/// 将已解析的SVG按各个尺寸渲染并打包
+///
+/// 图标中的PNG总是8位真彩色，`options.png` 的颜色类型与位深被忽略：ICO目录项按此填写位深，
+/// 且部分系统不接受索引色或灰度的图标帧。
+fn pack_tree(
+    tree: &resvg::usvg::Tree,
+    format: IconFormat,
+    sizes: &[u32],
+    options: &RenderOptions,
+) -> Result<Vec<u8>> {
+    let sizes = if sizes.is_empty() { format.default_sizes() } else { sizes };
+
+    let mut images = Vec::with_capacity(sizes.len());
+    for &size in sizes {
+        let options = RenderOptions {
+            width: Some(size),
+            height: Some(size),
+            crop: None,
+            format: OutputFormat::Png,
+            png: PngOptions { color: PngColor::Truecolor, bit_depth: 8, ..options.png.clone() },
+            ..options.clone()
+        };
+        let pixmap = svg2png::render_tree(tree, &options)?;
+        let dpi = svg2png::output_dpi(tree.size, &options)?;
+        images.push((size, svg2png::encode_png(&pixmap, dpi, &options)?));
+    }
+
+    match format {
+        IconFormat::Ico => pack_ico(&images, options.flatten),
+        IconFormat::Icns => pack_icns(&images),
+    }
+}
+
+/// 将多个 (边长, PNG数据) 打包为ICO文件
+///
+/// `flatten` 表示PNG为不透明的RGB图像，用于填写目录项中的位深。
+pub fn pack_ico(images: &[(u32, Vec<u8>)], flatten: bool) -> Result<Vec<u8>> {
+    let color_type = if flatten { ColorType::Rgb8 } else { ColorType::Rgba8 };
+    let frames = images.iter()
+        .map(|(size, png_data)| {
+            IcoFrame::with_encoded(png_data.as_slice(), *size, *size, color_type)
+                .map_err(|_| ConvertError::InvalidDimensions(format!("ICO图标的尺寸必须在1到256之间: {}", size)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut ico_data = Vec::new();
+    IcoEncoder::new(&mut ico_data)
+        .encode_images(&frames)
+        .map_err(|e| ConvertError::Encode(format!("ICO编码错误: {}", e)))?;
+    Ok(ico_data)
+}
+
+/// 将多个 (边长, PNG数据) 打包为ICNS文件
+///
+/// 支持的边长为16、32、64、128、256、512和1024。
+pub fn pack_icns(images: &[(u32, Vec<u8>)]) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    for (size, png_data) in images {
+        let icon_type = icns_type(*size)
+            .ok_or_else(|| ConvertError::InvalidDimensions(format!("ICNS不支持该图标尺寸: {}（可选: 16, 32, 64, 128, 256, 512, 1024）", size)))?;
+        body.extend_from_slice(icon_type);
+        body.extend_from_slice(&(png_data.len() as u32 + 8).to_be_bytes());
+        body.extend_from_slice(png_data);
+    }
+
+    let mut icns_data = Vec::with_capacity(body.len() + 8);
+    icns_data.extend_from_slice(b"icns");
+    icns_data.extend_from_slice(&(body.len() as u32 + 8).to_be_bytes());
+    icns_data.extend_from_slice(&body);
+    Ok(icns_data)
+}
+
+/// 各边长对应的ICNS图标类型（均为PNG存储的类型）
+fn icns_type(size: u32) -> Option<&'static [u8; 4]> {
+    match size {
+        16 => Some(b"icp4"),
+        32 => Some(b"icp5"),
+        64 => Some(b"icp6"),
+        128 => Some(b"ic07"),
+        256 => Some(b"ic08"),
+        512 => Some(b"ic09"),
+        1024 => Some(b"ic10"),
+        _ => None,
+    }
+}
+
+/// 将SVG文件转换为图标文件，`format` 为 `None` 时按输出文件扩展名推断（默认ICO）
+///
+/// 返回写入的文件路径与找不到的字体族。
+///
+/// 未设置资源目录时，引用的图像相对于输入文件所在目录查找。
+pub fn convert_svg_file_to_icon_file(
+    input_path: impl AsRef<Path>,
+    output_path: impl AsRef<Path>,
+    format: Option<IconFormat>,
+    sizes: &[u32],
+    options: &RenderOptions,
+) -> Result<FileReport> {
+    let (input_path, output_path) = (input_path.as_ref(), output_path.as_ref());
+    let format = format
+        .or_else(|| IconFormat::from_path(output_path))
+        .unwrap_or_default();
+    let svg_data = svg2png::read_svg_file(input_path, options.limits.as_ref())?;
+    let options = RenderOptions {
+        resources: options.resources.with_input_dir(input_path),
+        ..options.clone()
+    };
+    let (tree, missing_fonts) = svg2png::parse_input_with_missing_fonts(&svg_data, &options)?;
+    let icon_data = pack_tree(&tree, format, sizes, &options)?;
+    fs::write(output_path, icon_data)
+        .map_err(|e| ConvertError::io(format!("无法写入 {}", output_path.display()), e))?;
+    Ok(FileReport { written: vec![output_path.to_path_buf()], missing_fonts, ..Default::default() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ico_frames_are_truecolor_whatever_the_png_options() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="16"><circle cx="8" cy="8" r="6" fill="red"/></svg>"#;
+        for color in [PngColor::Palette, PngColor::Grayscale] {
+            let mut options = RenderOptions::default();
+            options.png.color = color;
+            let ico = convert_svg_to_icon(svg, IconFormat::Ico, &[16, 32], &options).unwrap();
+
+            let count = u16::from_le_bytes([ico[4], ico[5]]) as usize;
+            assert_eq!(count, 2);
+            for entry in ico[6..6 + 16 * count].chunks(16) {
+                // 目录项中的位深与PNG的IHDR一致：8位RGBA
+                assert_eq!(u16::from_le_bytes([entry[6], entry[7]]), 32);
+                let offset = u32::from_le_bytes(entry[12..16].try_into().unwrap()) as usize;
+                let png = &ico[offset..];
+                assert_eq!(&png[12..16], b"IHDR");
+                assert_eq!((png[24], png[25]), (8, 6), "{:?}", color);
+            }
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use super::error::{ConvertError, Result};
+
+/// 安全模式下默认允许的最大输入（10 MiB）
+pub const DEFAULT_MAX_INPUT_BYTES: usize = 10 * 1024 * 1024;
+
+/// 安全模式下默认允许的最大输出像素数（约2500万像素，RGBA约100 MB）
+pub const DEFAULT_MAX_PIXELS: u64 = 25_000_000;
+
+/// 安全模式下默认允许的最大元素数
+pub const DEFAULT_MAX_ELEMENTS: usize = 100_000;
+
+/// 安全模式下默认允许的最大嵌套深度（包括 `<use>` 展开后的深度）
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+/// 安全模式下默认的节点预算（`<use>` 等引用展开后的节点总数）
+pub const DEFAULT_MAX_NODES: u64 = 1_000_000;
+
+/// 处理不可信SVG时的资源上限
+///
+/// 超出任一上限时转换返回 `ConvertError::LimitExceeded`，而不是耗尽内存或栈空间。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// SVG数据的最大字节数
+    pub max_input_bytes: usize,
+    /// 单张输出图像的最大像素数（宽×高）
+    pub max_pixels: u64,
+    /// SVG中的最大元素数
+    pub max_elements: usize,
+    /// 元素的最大嵌套深度
+    pub max_depth: usize,
+    /// 引用展开后的最大节点数，限制渲染的工作量
+    pub max_nodes: u64,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_input_bytes: DEFAULT_MAX_INPUT_BYTES,
+            max_pixels: DEFAULT_MAX_PIXELS,
+            max_elements: DEFAULT_MAX_ELEMENTS,
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_nodes: DEFAULT_MAX_NODES,
+        }
+    }
+}
+
+/// 超出资源上限的错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LimitError {
+    /// 输入数据过大；从读取器读入时只读到上限多一个字节，`size` 为已读取的字节数
+    InputTooLarge { size: usize, max: usize },
+    /// 输出图像或引用的位图像素数过多
+    TooManyPixels { width: u32, height: u32, max: u64 },
+    /// 元素过多
+    TooManyElements { count: usize, max: usize },
+    /// 嵌套过深
+    NestingTooDeep { max: usize },
+    /// 引用展开后的节点数超出预算
+    NodeBudgetExceeded { max: u64 },
+    /// 安全模式不接受DTD（实体声明）
+    DtdNotAllowed,
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitError::InputTooLarge { size, max } =>
+                write!(f, "输入数据过大: {}字节（上限{}字节）", size, max),
+            LimitError::TooManyPixels { width, height, max } =>
+                write!(f, "图像过大: {}x{}（上限{}像素）", width, height, max),
+            LimitError::TooManyElements { count, max } =>
+                write!(f, "SVG元素过多: {}（上限{}）", count, max),
+            LimitError::NestingTooDeep { max } =>
+                write!(f, "SVG嵌套过深（上限{}层）", max),
+            LimitError::NodeBudgetExceeded { max } =>
+                write!(f, "SVG引用展开后的节点过多（上限{}）", max),
+            LimitError::DtdNotAllowed =>
+                write!(f, "安全模式不接受包含DTD的SVG"),
+        }
+    }
+}
+
+impl LimitError {
+    /// 稳定的上限类型名称
+    pub fn kind(&self) -> &'static str {
+        match self {
+            LimitError::InputTooLarge { .. } => "input_too_large",
+            LimitError::TooManyPixels { .. } => "too_many_pixels",
+            LimitError::TooManyElements { .. } => "too_many_elements",
+            LimitError::NestingTooDeep { .. } => "nesting_too_deep",
+            LimitError::NodeBudgetExceeded { .. } => "node_budget_exceeded",
+            LimitError::DtdNotAllowed => "dtd_not_allowed",
+        }
+    }
+}
+
+impl std::error::Error for LimitError {}
+
+impl Limits {
+    /// 在交给usvg解析之前检查SVG数据的大小、元素数、嵌套深度与引用展开后的节点数
+    ///
+    /// 展开计数覆盖 `<use>` 与属性中的 `url(#id)` 引用，每次引用按被引用元素的完整子树计入。
+    pub fn check_svg(&self, svg_data: &str) -> Result<()> {
+        if svg_data.len() > self.max_input_bytes {
+            return Err(LimitError::InputTooLarge { size: svg_data.len(), max: self.max_input_bytes }.into());
+        }
+
+        let doc = match roxmltree::Document::parse(svg_data) {
+            Ok(doc) => doc,
+            Err(roxmltree::Error::DtdDetected) => return Err(LimitError::DtdNotAllowed.into()),
+            Err(e) => return Err(ConvertError::from(e)),
+        };
+
+        let mut count = 0;
+        let mut ids = HashMap::new();
+        let mut stack = vec![(doc.root_element(), 1)];
+        while let Some((node, depth)) = stack.pop() {
+            count += 1;
+            if count > self.max_elements {
+                return Err(LimitError::TooManyElements { count, max: self.max_elements }.into());
+            }
+            if depth > self.max_depth {
+                return Err(LimitError::NestingTooDeep { max: self.max_depth }.into());
+            }
+            if let Some(id) = node.attribute("id") {
+                ids.entry(id).or_insert(node.id());
+            }
+            stack.extend(node.children().filter(|n| n.is_element()).map(|n| (n, depth + 1)));
+        }
+
+        let mut expansion = Expansion { doc: &doc, ids, memo: HashMap::new(), limits: self };
+        expansion.size(doc.root_element(), 1)?;
+        Ok(())
+    }
+
+    /// 检查输出图像的像素数
+    pub fn check_pixels(&self, width: u32, height: u32) -> Result<()> {
+        if width as u64 * height as u64 > self.max_pixels {
+            return Err(LimitError::TooManyPixels { width, height, max: self.max_pixels }.into());
+        }
+        Ok(())
+    }
+}
+
+/// 计算引用展开后的节点数与深度，结果按元素缓存
+struct Expansion<'a, 'input> {
+    doc: &'a roxmltree::Document<'input>,
+    ids: HashMap<&'a str, roxmltree::NodeId>,
+    /// 元素 → (展开后的节点数, 展开后的高度)；`None` 表示正在计算，用于跳过循环引用
+    memo: HashMap<roxmltree::NodeId, Option<(u64, usize)>>,
+    limits: &'a Limits,
+}
+
+impl<'a, 'input> Expansion<'a, 'input> {
+    /// 返回以 `node` 为根的展开后节点数与高度，`depth` 为 `node` 所在的深度
+    fn size(&mut self, node: roxmltree::Node<'a, 'input>, depth: usize) -> Result<(u64, usize)> {
+        if depth > self.limits.max_depth {
+            return Err(LimitError::NestingTooDeep { max: self.limits.max_depth }.into());
+        }
+        match self.memo.get(&node.id()) {
+            Some(Some((count, height))) => {
+                if depth + height - 1 > self.limits.max_depth {
+                    return Err(LimitError::NestingTooDeep { max: self.limits.max_depth }.into());
+                }
+                return Ok((*count, *height));
+            }
+            // 循环引用由usvg忽略，不计入
+            Some(None) => return Ok((0, 0)),
+            None => {}
+        }
+        self.memo.insert(node.id(), None);
+
+        let mut count: u64 = 1;
+        let mut height = 0;
+        let references: Vec<roxmltree::NodeId> = node.attributes()
+            .filter_map(|attr| reference_id(attr.name(), attr.value()))
+            .filter_map(|id| self.ids.get(id).copied())
+            .collect();
+        let children = node.children()
+            .filter(|n| n.is_element())
+            .chain(references.into_iter().filter_map(|id| self.doc.get_node(id)));
+        for child in children {
+            let (child_count, child_height) = self.size(child, depth + 1)?;
+            count = count.saturating_add(child_count);
+            height = height.max(child_height);
+            if count > self.limits.max_nodes {
+                return Err(LimitError::NodeBudgetExceeded { max: self.limits.max_nodes }.into());
+            }
+        }
+
+        self.memo.insert(node.id(), Some((count, height + 1)));
+        Ok((count, height + 1))
+    }
+}
+
+/// 属性中引用的元素id：`href="#id"` 或 `url(#id)`
+fn reference_id<'v>(name: &str, value: &'v str) -> Option<&'v str> {
+    if name == "href" {
+        return value.strip_prefix('#');
+    }
+    let start = value.find("url(")? + 4;
+    let end = start + value[start..].find(')')?;
+    value[start..end].trim().trim_matches(['\'', '"']).strip_prefix('#')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit_error(limits: &Limits, svg: &str) -> LimitError {
+        match limits.check_svg(svg) {
+            Err(ConvertError::LimitExceeded(error)) => error,
+            other => panic!("应超出上限，实际为 {:?}", other),
+        }
+    }
+
+    /// 每层 `count` 个 `<use>` 引用上一层，共 `levels` 层（billion laughs 的SVG形式）
+    fn use_fan_out(levels: usize, count: usize) -> String {
+        let mut svg = String::from(r#"<svg xmlns="http://www.w3.org/2000/svg"><defs><rect id="l0" width="1" height="1"/>"#);
+        for level in 1..=levels {
+            svg.push_str(&format!(r#"<g id="l{}">"#, level));
+            for _ in 0..count {
+                svg.push_str(&format!(r##"<use href="#l{}"/>"##, level - 1));
+            }
+            svg.push_str("</g>");
+        }
+        svg.push_str(&format!(r##"</defs><use href="#l{}"/></svg>"##, levels));
+        svg
+    }
+
+    #[test]
+    fn use_fan_out_exceeds_node_budget() {
+        // 10层×10个引用展开后约10^10个节点，文档本身只有约100个元素
+        let svg = use_fan_out(10, 10);
+        assert!(svg.len() < 4096);
+        assert_eq!(limit_error(&Limits::default(), &svg), LimitError::NodeBudgetExceeded { max: DEFAULT_MAX_NODES });
+    }
+
+    #[test]
+    fn small_fan_out_is_accepted() {
+        assert!(Limits::default().check_svg(&use_fan_out(3, 10)).is_ok());
+    }
+
+    #[test]
+    fn fan_out_through_url_references_is_counted() {
+        let limits = Limits { max_nodes: 100, ..Default::default() };
+        let mut svg = String::from(r#"<svg xmlns="http://www.w3.org/2000/svg"><defs><pattern id="p" width="1" height="1">"#);
+        svg.push_str(&r#"<rect width="1" height="1"/>"#.repeat(20));
+        svg.push_str("</pattern></defs>");
+        svg.push_str(&r#"<rect width="1" height="1" fill="url(#p)"/>"#.repeat(10));
+        svg.push_str("</svg>");
+        assert_eq!(limit_error(&limits, &svg), LimitError::NodeBudgetExceeded { max: 100 });
+    }
+
+    #[test]
+    fn cyclic_references_terminate() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg">
+            <g id="a"><use href="#b"/></g>
+            <g id="b"><use href="#a"/></g>
+            <use id="c" href="#c"/>
+            <rect width="1" height="1" fill="url(#a)"/>
+        </svg>"##;
+        assert!(Limits::default().check_svg(svg).is_ok());
+    }
+
+    #[test]
+    fn deep_nesting_is_rejected() {
+        let depth = DEFAULT_MAX_DEPTH + 10;
+        let svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg">{}{}</svg>"#,
+            "<g>".repeat(depth),
+            "</g>".repeat(depth),
+        );
+        assert_eq!(limit_error(&Limits::default(), &svg), LimitError::NestingTooDeep { max: DEFAULT_MAX_DEPTH });
+    }
+
+    #[test]
+    fn depth_added_by_references_is_counted() {
+        // 每个元素只嵌套两层，但引用链展开后深度超过上限
+        let limits = Limits { max_depth: 16, ..Default::default() };
+        let mut svg = String::from(r#"<svg xmlns="http://www.w3.org/2000/svg"><defs><rect id="l0" width="1" height="1"/>"#);
+        for level in 1..=20 {
+            svg.push_str(&format!(r##"<g id="l{}"><use href="#l{}"/></g>"##, level, level - 1));
+        }
+        svg.push_str(r##"</defs><use href="#l20"/></svg>"##);
+        assert_eq!(limit_error(&limits, &svg), LimitError::NestingTooDeep { max: 16 });
+    }
+
+    #[test]
+    fn dtd_is_rejected() {
+        let svg = r#"<?xml version="1.0"?>
+            <!DOCTYPE svg [<!ENTITY a "aaaaaaaaaa"><!ENTITY b "&a;&a;&a;&a;&a;&a;&a;&a;&a;&a;">]>
+            <svg xmlns="http://www.w3.org/2000/svg"><text>&b;</text></svg>"#;
+        assert_eq!(limit_error(&Limits::default(), svg), LimitError::DtdNotAllowed);
+    }
+
+    #[test]
+    fn element_count_and_input_size_are_checked() {
+        let svg = format!(r#"<svg xmlns="http://www.w3.org/2000/svg">{}</svg>"#, "<g/>".repeat(20));
+        let limits = Limits { max_elements: 10, ..Default::default() };
+        assert_eq!(limit_error(&limits, &svg), LimitError::TooManyElements { count: 11, max: 10 });
+
+        let limits = Limits { max_input_bytes: 16, ..Default::default() };
+        assert_eq!(limit_error(&limits, &svg), LimitError::InputTooLarge { size: svg.len(), max: 16 });
+    }
+}
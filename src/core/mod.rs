@@ -1,2 +1,16 @@
+pub mod error;
 pub mod svg2png;
-pub mod png2svg; 
\ No newline at end of file
+pub mod icon;
+pub mod fonts;
+pub mod limits;
+pub mod resources;
+pub mod png2svg;
+pub mod raster;
+pub mod quantize;
+pub mod color;
+pub mod trace;
+pub mod fit;
+pub mod simplify;
+pub mod path;
+pub mod speckle;
+pub mod palette;
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+
+/// 为带透明度的像素生成调色板，返回调色板以及每个像素对应的调色板下标
+///
+/// 颜色数不超过 `max_colors`（上限256）时调色板与原图完全一致，否则在RGBA空间中做中位切分。
+/// 完全透明的像素统一为 `[0, 0, 0, 0]`；调色板中半透明的颜色排在前面，便于缩短tRNS块。
+pub fn build_palette(pixels: &[[u8; 4]], max_colors: usize) -> (Vec<[u8; 4]>, Vec<u8>) {
+    let max_colors = max_colors.clamp(1, 256);
+
+    let mut histogram: HashMap<[u8; 4], u64> = HashMap::new();
+    for &pixel in pixels {
+        *histogram.entry(normalize(pixel)).or_default() += 1;
+    }
+    let mut colors: Vec<([u8; 4], u64)> = histogram.into_iter().collect();
+    // 固定顺序，使结果与哈希表的遍历顺序无关
+    colors.sort_unstable();
+
+    let mut palette: Vec<[u8; 4]> = if colors.len() <= max_colors {
+        colors.iter().map(|&(color, _)| color).collect()
+    } else {
+        median_cut(colors.clone(), max_colors)
+    };
+    palette.sort_by_key(|color| (color[3] == 255, *color));
+    palette.dedup();
+
+    // 每种原始颜色只查找一次最近的调色板颜色
+    let lookup: HashMap<[u8; 4], u8> = colors.iter()
+        .map(|&(color, _)| (color, nearest(color, &palette)))
+        .collect();
+    let indices = pixels.iter().map(|&pixel| lookup[&normalize(pixel)]).collect();
+
+    (palette, indices)
+}
+
+fn normalize(pixel: [u8; 4]) -> [u8; 4] {
+    if pixel[3] == 0 { [0, 0, 0, 0] } else { pixel }
+}
+
+/// RGBA中位切分：反复选择跨度最大的颜色盒，沿该通道在像素数的中位处切开
+fn median_cut(colors: Vec<([u8; 4], u64)>, max_colors: usize) -> Vec<[u8; 4]> {
+    let mut boxes = vec![colors];
+
+    while boxes.len() < max_colors {
+        let Some((index, channel)) = boxes.iter()
+            .enumerate()
+            .filter(|(_, colors)| colors.len() > 1)
+            .map(|(i, colors)| {
+                let (channel, range) = widest_channel(colors);
+                (i, channel, range)
+            })
+            .max_by_key(|&(_, _, range)| range)
+            .map(|(i, channel, _)| (i, channel))
+        else {
+            break;
+        };
+
+        let mut colors = boxes.swap_remove(index);
+        colors.sort_unstable_by_key(|&(color, _)| color[channel]);
+        let total: u64 = colors.iter().map(|&(_, n)| n).sum();
+        let mut count = 0;
+        let split = colors.iter()
+            .position(|&(_, n)| {
+                count += n;
+                count * 2 >= total
+            })
+            .map_or(1, |i| i + 1)
+            .clamp(1, colors.len() - 1);
+        let upper = colors.split_off(split);
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+
+    boxes.iter().map(|colors| weighted_mean(colors)).collect()
+}
+
+/// 返回跨度最大的通道及其跨度
+fn widest_channel(colors: &[([u8; 4], u64)]) -> (usize, u8) {
+    (0..4)
+        .map(|channel| {
+            let min = colors.iter().map(|(c, _)| c[channel]).min().unwrap_or(0);
+            let max = colors.iter().map(|(c, _)| c[channel]).max().unwrap_or(0);
+            (channel, max - min)
+        })
+        .max_by_key(|&(_, range)| range)
+        .unwrap_or((0, 0))
+}
+
+fn weighted_mean(colors: &[([u8; 4], u64)]) -> [u8; 4] {
+    let total: u64 = colors.iter().map(|&(_, n)| n).sum::<u64>().max(1);
+    let mut sum = [0u64; 4];
+    for &(color, n) in colors {
+        for channel in 0..4 {
+            sum[channel] += color[channel] as u64 * n;
+        }
+    }
+    normalize(sum.map(|s| ((s + total / 2) / total) as u8))
+}
+
+/// 按预乘颜色比较距离，透明度低的像素颜色差异影响较小
+fn nearest(color: [u8; 4], palette: &[[u8; 4]]) -> u8 {
+    let premultiply = |c: [u8; 4]| {
+        let a = c[3] as i32;
+        [c[0] as i32 * a / 255, c[1] as i32 * a / 255, c[2] as i32 * a / 255, a]
+    };
+    let target = premultiply(color);
+    palette.iter()
+        .enumerate()
+        .min_by_key(|(_, &entry)| {
+            let p = premultiply(entry);
+            (0..4).map(|i| (p[i] - target[i]).pow(2)).sum::<i32>()
+        })
+        .map_or(0, |(i, _)| i as u8)
+}
@@ -0,0 +1,270 @@
+use super::error::{ConvertError, Result};
+use kurbo::{BezPath, PathEl, Point, Rect, Shape};
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// 计算曲线周长时的精度（像素）
+const PERIMETER_ACCURACY: f64 = 0.1;
+
+/// SVG填充规则
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FillRule {
+    /// 非零环绕规则，依赖孔洞轮廓与外轮廓方向相反
+    NonZero,
+    /// 奇偶规则
+    #[default]
+    EvenOdd,
+}
+
+impl FillRule {
+    /// SVG `fill-rule` 属性值
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FillRule::NonZero => "nonzero",
+            FillRule::EvenOdd => "evenodd",
+        }
+    }
+}
+
+impl FromStr for FillRule {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "nonzero" => Ok(FillRule::NonZero),
+            "evenodd" => Ok(FillRule::EvenOdd),
+            _ => Err(ConvertError::InvalidOption(format!("未知的填充规则: {}（可选: nonzero, evenodd）", s))),
+        }
+    }
+}
+
+/// 路径段，起点为上一段的终点
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Segment {
+    /// 直线
+    Line(Point),
+    /// 二次贝塞尔曲线（控制点，终点）
+    Quad(Point, Point),
+    /// 三次贝塞尔曲线（控制点1，控制点2，终点）
+    Cubic(Point, Point, Point),
+}
+
+impl Segment {
+    /// 段的终点
+    pub fn end(&self) -> Point {
+        match *self {
+            Segment::Line(p) | Segment::Quad(_, p) | Segment::Cubic(_, _, p) => p,
+        }
+    }
+}
+
+/// 轮廓方向（屏幕坐标系，y 轴向下）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    /// 顺时针，外轮廓的方向
+    Clockwise,
+    /// 逆时针，孔洞轮廓的方向
+    CounterClockwise,
+}
+
+/// 一条子路径
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubPath {
+    /// 起点
+    pub start: Point,
+    /// 依次相连的路径段
+    pub segments: Vec<Segment>,
+    /// 是否闭合
+    pub closed: bool,
+}
+
+impl SubPath {
+    /// 由多边形顶点构造闭合的折线子路径
+    pub fn polygon(points: &[(f32, f32)]) -> Self {
+        let mut points = points.iter().map(|&(x, y)| Point::new(x as f64, y as f64));
+        let start = points.next().unwrap_or(Point::ZERO);
+        SubPath {
+            start,
+            segments: points.map(Segment::Line).collect(),
+            closed: true,
+        }
+    }
+
+    /// 由 kurbo 路径构造，只读取第一条子路径
+    pub fn from_bezpath(path: &BezPath) -> Self {
+        let mut sub = SubPath {
+            start: Point::ZERO,
+            segments: Vec::new(),
+            closed: false,
+        };
+        for (i, element) in path.elements().iter().enumerate() {
+            match *element {
+                PathEl::MoveTo(p) if i == 0 => sub.start = p,
+                PathEl::MoveTo(_) => break,
+                PathEl::LineTo(p) => sub.segments.push(Segment::Line(p)),
+                PathEl::QuadTo(p1, p2) => sub.segments.push(Segment::Quad(p1, p2)),
+                PathEl::CurveTo(p1, p2, p3) => sub.segments.push(Segment::Cubic(p1, p2, p3)),
+                PathEl::ClosePath => {
+                    sub.closed = true;
+                    break;
+                }
+            }
+        }
+        sub
+    }
+
+    /// 转换为 kurbo 路径
+    pub fn to_bezpath(&self) -> BezPath {
+        let mut path = BezPath::new();
+        self.append_to(&mut path);
+        path
+    }
+
+    fn append_to(&self, path: &mut BezPath) {
+        path.move_to(self.start);
+        for segment in &self.segments {
+            match *segment {
+                Segment::Line(p) => path.line_to(p),
+                Segment::Quad(p1, p2) => path.quad_to(p1, p2),
+                Segment::Cubic(p1, p2, p3) => path.curve_to(p1, p2, p3),
+            }
+        }
+        if self.closed {
+            path.close_path();
+        }
+    }
+
+    /// 起点与各段终点
+    pub fn vertices(&self) -> Vec<Point> {
+        std::iter::once(self.start)
+            .chain(self.segments.iter().map(Segment::end))
+            .collect()
+    }
+
+    /// 包围盒（包含曲线的极值点）
+    pub fn bbox(&self) -> Rect {
+        self.to_bezpath().bounding_box()
+    }
+
+    /// 有向面积，顺时针为正；未闭合的路径按首尾相连计算
+    pub fn signed_area(&self) -> f64 {
+        let mut path = self.to_bezpath();
+        if !self.closed {
+            path.close_path();
+        }
+        path.area()
+    }
+
+    /// 面积
+    pub fn area(&self) -> f64 {
+        self.signed_area().abs()
+    }
+
+    /// 周长（闭合路径包含回到起点的一段）
+    pub fn perimeter(&self) -> f64 {
+        self.to_bezpath().perimeter(PERIMETER_ACCURACY)
+    }
+
+    /// 方向
+    pub fn orientation(&self) -> Orientation {
+        if self.signed_area() >= 0.0 {
+            Orientation::Clockwise
+        } else {
+            Orientation::CounterClockwise
+        }
+    }
+
+    /// 写为SVG路径数据
+    pub fn write_path_data(&self, out: &mut String) {
+        let _ = write!(out, "M{},{}", format_coord(self.start.x), format_coord(self.start.y));
+        for segment in &self.segments {
+            let _ = match *segment {
+                Segment::Line(p) => write!(out, " L{},{}", format_coord(p.x), format_coord(p.y)),
+                Segment::Quad(p1, p2) => write!(
+                    out, " Q{},{} {},{}",
+                    format_coord(p1.x), format_coord(p1.y), format_coord(p2.x), format_coord(p2.y),
+                ),
+                Segment::Cubic(p1, p2, p3) => write!(
+                    out, " C{},{} {},{} {},{}",
+                    format_coord(p1.x), format_coord(p1.y), format_coord(p2.x), format_coord(p2.y),
+                    format_coord(p3.x), format_coord(p3.y),
+                ),
+            };
+        }
+        if self.closed {
+            out.push('Z');
+        }
+    }
+}
+
+/// 带孔洞的填充区域
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    /// 外轮廓（顺时针）
+    pub outer: SubPath,
+    /// 孔洞轮廓（逆时针）
+    pub holes: Vec<SubPath>,
+}
+
+impl Region {
+    /// 填充面积，即外轮廓面积减去孔洞面积
+    pub fn area(&self) -> f64 {
+        let holes: f64 = self.holes.iter().map(SubPath::area).sum();
+        (self.outer.area() - holes).max(0.0)
+    }
+
+    /// 外轮廓与孔洞轮廓的周长之和
+    pub fn perimeter(&self) -> f64 {
+        self.outer.perimeter() + self.holes.iter().map(SubPath::perimeter).sum::<f64>()
+    }
+
+    /// 包围盒
+    pub fn bbox(&self) -> Rect {
+        self.outer.bbox()
+    }
+
+    /// 转换为 kurbo 路径，孔洞作为子路径
+    pub fn to_bezpath(&self) -> BezPath {
+        let mut path = BezPath::new();
+        for sub in std::iter::once(&self.outer).chain(&self.holes) {
+            sub.append_to(&mut path);
+        }
+        path
+    }
+
+    /// SVG路径数据，孔洞作为子路径
+    pub fn to_path_data(&self) -> String {
+        let mut out = String::new();
+        for sub in std::iter::once(&self.outer).chain(&self.holes) {
+            sub.write_path_data(&mut out);
+        }
+        out
+    }
+}
+
+/// 单一颜色的图层
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layer {
+    /// 填充颜色（RGBA）
+    pub color: [u8; 4],
+    /// 图层中的区域
+    pub regions: Vec<Region>,
+}
+
+/// 矢量化结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct VectorImage {
+    /// 宽度（像素）
+    pub width: u32,
+    /// 高度（像素）
+    pub height: u32,
+    /// 从下到上绘制的图层
+    pub layers: Vec<Layer>,
+}
+
+/// 保留两位小数并去掉多余的零
+fn format_coord(value: f64) -> String {
+    let text = format!("{:.2}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" { "0".to_string() } else { text.to_string() }
+}
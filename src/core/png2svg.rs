@@ -1,40 +1,392 @@
-use anyhow::Result;
+use super::error::{ConvertError, Result};
 use image::{GenericImageView, RgbaImage, Rgba, GrayImage, Luma, DynamicImage};
 use svg::Document;
 use svg::node::element::Path as SvgPath;
-use std::fmt::Write;
 use std::collections::HashMap;
 use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use super::color::{ColorMetric, ColorSpace, DeltaE};
+use super::fit::fit_contour;
+pub use super::path::FillRule;
+use super::path::{Layer, Region, SubPath, VectorImage};
+use super::simplify::{SimplifyMethod, level_to_tolerance, simplify_closed};
+use super::quantize::Quantizer;
+use super::speckle::remove_speckles;
+use super::trace::{signed_area, trace_contours};
+
+/// 保留的最小区域面积（平方像素）
+const MIN_REGION_AREA: f64 = 1.0;
+
+/// 4×4 Bayer 有序抖动矩阵
+const BAYER_4X4: [[f32; 4]; 4] = [
+    [0.0, 8.0, 2.0, 10.0],
+    [12.0, 4.0, 14.0, 6.0],
+    [3.0, 11.0, 1.0, 9.0],
+    [15.0, 7.0, 13.0, 5.0],
+];
+
+/// 像素到图层的分配方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Assignment {
+    /// 每个不透明像素归入最近的调色板颜色，图层之间互不重叠且没有空洞
+    #[default]
+    Nearest,
+    /// 像素归入所有色差小于阈值的图层，可能重叠或遗漏
+    Threshold,
+}
+
+impl FromStr for Assignment {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "nearest" => Ok(Assignment::Nearest),
+            "threshold" => Ok(Assignment::Threshold),
+            _ => Err(ConvertError::InvalidOption(format!("未知的分配方式: {}（可选: nearest, threshold）", s))),
+        }
+    }
+}
+
+/// 最近色分配时使用的抖动算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dither {
+    /// 不抖动
+    #[default]
+    None,
+    /// Floyd–Steinberg 误差扩散
+    FloydSteinberg,
+    /// 4×4 Bayer 有序抖动
+    Ordered,
+}
+
+impl FromStr for Dither {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" | "off" => Ok(Dither::None),
+            "floyd-steinberg" | "fs" => Ok(Dither::FloydSteinberg),
+            "ordered" | "bayer" => Ok(Dither::Ordered),
+            _ => Err(ConvertError::InvalidOption(format!("未知的抖动算法: {}（可选: none, floyd-steinberg, ordered）", s))),
+        }
+    }
+}
+
+/// 颜色分层选项
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorOptions {
+    /// 调色板量化算法
+    pub quantizer: Quantizer,
+    /// 调色板最大颜色数
+    pub max_colors: usize,
+    /// 量化与图层分配所用的颜色距离
+    pub metric: ColorMetric,
+    /// 像素到图层的分配方式
+    pub assignment: Assignment,
+    /// 最近色分配时的抖动算法
+    pub dither: Dither,
+    /// 像素归入图层的最大色差（仅用于 `Assignment::Threshold`）
+    pub threshold: f32,
+    /// 小于该面积（像素）的连通区域在追踪前被消除，0 或 1 表示不过滤（默认）
+    pub min_area: u32,
+    /// 是否将小区域并入主导邻色（仅用于 `Assignment::Nearest`），否则直接删除
+    pub merge_small: bool,
+    /// 不透明度低于该值的像素视为透明
+    pub alpha_threshold: u8,
+}
+
+impl Default for ColorOptions {
+    fn default() -> Self {
+        ColorOptions {
+            quantizer: Quantizer::default(),
+            max_colors: 8,
+            metric: ColorMetric::default(),
+            assignment: Assignment::default(),
+            dither: Dither::default(),
+            threshold: 20.0,
+            min_area: 0,
+            merge_small: true,
+            alpha_threshold: 128,
+        }
+    }
+}
+
+/// 路径输出选项
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathOptions {
+    /// 简化级别（0-10），未设置 `simplify_tolerance` 时换算为像素容差
+    pub simplify: u8,
+    /// 带孔洞路径使用的填充规则
+    pub fill_rule: FillRule,
+    /// 是否将轮廓拟合为贝塞尔曲线（关闭时输出折线）
+    pub curves: bool,
+    /// 曲线拟合允许的最大误差（像素）
+    pub curve_tolerance: f32,
+    /// 方向改变超过该角度（度）的顶点保留为尖角
+    pub corner_angle: f32,
+    /// 轮廓简化算法
+    pub simplify_method: SimplifyMethod,
+    /// 显式的简化容差（像素），设置后覆盖简化级别
+    pub simplify_tolerance: Option<f32>,
+}
+
+impl Default for PathOptions {
+    fn default() -> Self {
+        PathOptions {
+            simplify: 3,
+            fill_rule: FillRule::default(),
+            curves: true,
+            curve_tolerance: 1.0,
+            corner_angle: 60.0,
+            simplify_method: SimplifyMethod::default(),
+            simplify_tolerance: None,
+        }
+    }
+}
+
+/// 矢量化选项，贯穿颜色分层与路径生成的各个阶段
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TraceOptions {
+    /// 颜色分层选项
+    pub color: ColorOptions,
+    /// 路径输出选项
+    pub path: PathOptions,
+}
+
+impl TraceOptions {
+    /// 从默认选项开始构建
+    pub fn builder() -> TraceOptionsBuilder {
+        TraceOptionsBuilder::default()
+    }
+
+    /// 以当前选项为起点继续构建
+    pub fn into_builder(self) -> TraceOptionsBuilder {
+        TraceOptionsBuilder { options: self }
+    }
+
+    /// 预设对应的选项
+    pub fn preset(preset: TracePreset) -> Self {
+        let mut options = TraceOptions::default();
+        match preset {
+            TracePreset::Logo => {}
+            TracePreset::Photo => {
+                options.color.quantizer = Quantizer::KMeans;
+                options.color.max_colors = 16;
+                options.color.min_area = 16;
+                options.path.curve_tolerance = 1.5;
+            }
+            TracePreset::LineArt => {
+                options.color.max_colors = 2;
+                options.color.min_area = 8;
+                options.path.simplify = 2;
+                options.path.curve_tolerance = 0.75;
+                options.path.corner_angle = 50.0;
+            }
+            TracePreset::PixelArt => {
+                options.color.quantizer = Quantizer::Octree;
+                options.color.max_colors = 32;
+                options.color.min_area = 0;
+                options.path.simplify = 0;
+                options.path.curves = false;
+            }
+        }
+        options
+    }
+}
+
+/// 常见图像类型的选项预设
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TracePreset {
+    /// 色块分明的标志与图标（即默认选项）
+    #[default]
+    Logo,
+    /// 照片：更多颜色并合并较大的噪点
+    Photo,
+    /// 线稿：双色并保留细节
+    LineArt,
+    /// 像素画：保留每个像素的直角边界，不简化也不拟合曲线
+    PixelArt,
+}
+
+impl FromStr for TracePreset {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "logo" => Ok(TracePreset::Logo),
+            "photo" => Ok(TracePreset::Photo),
+            "lineart" | "line-art" => Ok(TracePreset::LineArt),
+            "pixel-art" | "pixelart" => Ok(TracePreset::PixelArt),
+            _ => Err(ConvertError::InvalidOption(format!("未知的预设: {}（可选: logo, photo, lineart, pixel-art）", s))),
+        }
+    }
+}
+
+/// `TraceOptions` 的构建器
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TraceOptionsBuilder {
+    options: TraceOptions,
+}
+
+impl TraceOptionsBuilder {
+    /// 以预设为起点构建，之后的设置覆盖预设中的取值
+    pub fn from_preset(preset: TracePreset) -> Self {
+        TraceOptions::preset(preset).into_builder()
+    }
+
+    /// 简化级别（0-10）
+    pub fn simplify(mut self, level: u8) -> Self {
+        self.options.path.simplify = level;
+        self
+    }
+
+    /// 调色板量化算法
+    pub fn quantizer(mut self, quantizer: Quantizer) -> Self {
+        self.options.color.quantizer = quantizer;
+        self
+    }
+
+    /// 调色板最大颜色数
+    pub fn colors(mut self, max_colors: usize) -> Self {
+        self.options.color.max_colors = max_colors;
+        self
+    }
+
+    /// 颜色空间
+    pub fn color_space(mut self, space: ColorSpace) -> Self {
+        self.options.color.metric.space = space;
+        self
+    }
+
+    /// 色差公式
+    pub fn delta_e(mut self, delta_e: DeltaE) -> Self {
+        self.options.color.metric.delta_e = Some(delta_e);
+        self
+    }
+
+    /// 像素到图层的分配方式
+    pub fn assignment(mut self, assignment: Assignment) -> Self {
+        self.options.color.assignment = assignment;
+        self
+    }
+
+    /// 最近色分配时的抖动算法
+    pub fn dither(mut self, dither: Dither) -> Self {
+        self.options.color.dither = dither;
+        self
+    }
+
+    /// 像素归入图层的最大色差（仅用于 `Assignment::Threshold`）
+    pub fn threshold(mut self, threshold: f32) -> Self {
+        self.options.color.threshold = threshold;
+        self
+    }
+
+    /// 追踪前消除的连通区域面积上限（像素）
+    pub fn min_area(mut self, min_area: u32) -> Self {
+        self.options.color.min_area = min_area;
+        self
+    }
+
+    /// 是否将小区域并入主导邻色
+    pub fn merge_small(mut self, merge_small: bool) -> Self {
+        self.options.color.merge_small = merge_small;
+        self
+    }
+
+    /// 视为透明的不透明度上限
+    pub fn alpha_threshold(mut self, alpha_threshold: u8) -> Self {
+        self.options.color.alpha_threshold = alpha_threshold;
+        self
+    }
+
+    /// 带孔洞路径的填充规则
+    pub fn fill_rule(mut self, fill_rule: FillRule) -> Self {
+        self.options.path.fill_rule = fill_rule;
+        self
+    }
+
+    /// 是否拟合贝塞尔曲线
+    pub fn curves(mut self, curves: bool) -> Self {
+        self.options.path.curves = curves;
+        self
+    }
+
+    /// 曲线拟合允许的最大误差（像素）
+    pub fn curve_tolerance(mut self, tolerance: f32) -> Self {
+        self.options.path.curve_tolerance = tolerance;
+        self
+    }
+
+    /// 保留为尖角的最小转角（度）
+    pub fn corner_angle(mut self, angle: f32) -> Self {
+        self.options.path.corner_angle = angle;
+        self
+    }
+
+    /// 轮廓简化算法
+    pub fn simplify_method(mut self, method: SimplifyMethod) -> Self {
+        self.options.path.simplify_method = method;
+        self
+    }
+
+    /// 显式的简化容差（像素），覆盖简化级别
+    pub fn simplify_tolerance(mut self, tolerance: f32) -> Self {
+        self.options.path.simplify_tolerance = Some(tolerance);
+        self
+    }
+
+    /// 完成构建
+    pub fn build(self) -> TraceOptions {
+        self.options
+    }
+}
+
+/// 将位图转换为SVG数据
+pub fn convert_png_to_svg(img: &DynamicImage, options: &TraceOptions) -> Result<String> {
+    let image = trace_image(img, options)?;
+    write_svg(&image, options.path.fill_rule)
+}
+
+/// 将图像矢量化为结构化的路径数据
+pub fn trace_image(img: &DynamicImage, options: &TraceOptions) -> Result<VectorImage> {
+    options.color.metric.validate()?;
 
-/// 将PNG转换为SVG数据
-pub fn convert_png_to_svg(
-    img: &DynamicImage,
-    simplify: u8,
-) -> Result<String> {
     // 获取图像尺寸
     let (width, height) = img.dimensions();
     
+    // 颜色分离和路径追踪
+    let layers = create_color_layers(img, &options.color)
+        .into_iter()
+        .map(|(layer, color)| Layer {
+            color,
+            regions: trace_layer(&layer, &options.path),
+        })
+        .collect();
+    
+    Ok(VectorImage { width, height, layers })
+}
+
+/// 将矢量化结果序列化为SVG
+pub fn write_svg(image: &VectorImage, fill_rule: FillRule) -> Result<String> {
     // 创建SVG文档
     let mut document = Document::new()
-        .set("width", width)
-        .set("height", height)
-        .set("viewBox", (0, 0, width, height));
-    
-    // 颜色分离和路径追踪
-    let layers = create_color_layers(img);
+        .set("width", image.width)
+        .set("height", image.height)
+        .set("viewBox", (0, 0, image.width, image.height));
     
-    for (layer, color) in layers {
-        let layer_paths = trace_layer(&layer);
-        
-        // 应用路径简化
-        let simplified_paths = simplify_paths(&layer_paths, simplify);
-        
-        for path in simplified_paths {
+    for layer in &image.layers {
+        let color = layer.color;
+        for region in &layer.regions {
             let path_element = SvgPath::new()
                 .set("fill", format!("rgba({},{},{},{})", 
                      color[0], color[1], color[2], color[3] as f64 / 255.0))
+                .set("fill-rule", fill_rule.as_str())
                 .set("stroke", "none")
-                .set("d", path);
+                .set("d", region.to_path_data());
             document = document.add(path_element);
         }
     }
@@ -42,61 +394,226 @@ pub fn convert_png_to_svg(
     // 转换为字符串
     let mut output = Vec::new();
     svg::write(&mut output, &document)?;
-    let svg_string = String::from_utf8(output)?;
+    let svg_string = String::from_utf8(output)
+        .map_err(|e| ConvertError::Encode(format!("SVG编码错误: {}", e)))?;
     
     Ok(svg_string)
 }
 
 /// 从PNG文件转换为SVG文件
+///
+/// 与 `convert_raster_file_to_svg_file` 相同，同样接受其他位图格式。
 pub fn convert_png_file_to_svg_file(
-    input_path: &str,
-    output_path: &str,
-    simplify: u8,
+    input_path: impl AsRef<Path>,
+    output_path: impl AsRef<Path>,
+    options: &TraceOptions,
 ) -> Result<()> {
-    // 读取输入PNG文件
-    let img = image::open(input_path)?;
+    convert_raster_file_to_svg_file(input_path, output_path, None, options)
+}
+
+/// 从位图文件（PNG、JPEG、GIF、BMP、WebP、TIFF、ICO）转换为SVG文件
+///
+/// 格式由文件内容识别；`frame` 为GIF动画的帧序号，省略时取第一帧。
+pub fn convert_raster_file_to_svg_file(
+    input_path: impl AsRef<Path>,
+    output_path: impl AsRef<Path>,
+    frame: Option<usize>,
+    options: &TraceOptions,
+) -> Result<()> {
+    // 读取并解码输入图像
+    let img = super::raster::load_raster_file(input_path, frame)?;
     
     // 使用核心函数进行转换
-    let svg_data = convert_png_to_svg(&img, simplify)?;
+    let svg_data = convert_png_to_svg(&img, options)?;
     
     // 保存SVG文件
-    fs::write(output_path, svg_data)?;
+    let output_path = output_path.as_ref();
+    fs::write(output_path, svg_data)
+        .map_err(|e| ConvertError::io(format!("无法写入 {}", output_path.display()), e))?;
     
     Ok(())
 }
 
+/// 从读取器读入位图数据，将生成的SVG写入写入器
+///
+/// 格式由数据内容识别，`frame` 的含义与 `convert_raster_file_to_svg_file` 相同。
+pub fn convert_raster_reader_to_svg_writer(
+    mut reader: impl Read,
+    mut writer: impl Write,
+    frame: Option<usize>,
+    options: &TraceOptions,
+) -> Result<()> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    let img = super::raster::decode_raster(&data, frame)?;
+    let svg_data = convert_png_to_svg(&img, options)?;
+    writer.write_all(svg_data.as_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
+
 /// 创建颜色图层
-pub fn create_color_layers(img: &DynamicImage) -> Vec<(RgbaImage, [u8; 4])> {
-    // 获取图像尺寸
-    let (width, height) = img.dimensions();
-    
+pub fn create_color_layers(img: &DynamicImage, options: &ColorOptions) -> Vec<(RgbaImage, [u8; 4])> {
     // 转换为RGBA以便于处理
     let rgba = img.to_rgba8();
     
     // 量化颜色（减少颜色数量）
-    let colors = quantize_colors(&rgba, 8);
+    let colors = options.quantizer.quantize(&rgba, options.max_colors, &options.metric, options.alpha_threshold);
+    
+    match options.assignment {
+        Assignment::Nearest => {
+            let mut labels = assign_nearest(&rgba, &colors, options);
+            remove_speckles(&mut labels, rgba.width(), options.min_area, options.merge_small);
+            layers_from_labels(&rgba, &colors, &labels)
+        }
+        Assignment::Threshold => threshold_layers(&rgba, &colors, options),
+    }
+}
+
+/// 将每个不透明像素映射到最近的调色板颜色，返回逐像素的调色板下标（透明像素为 `None`）
+pub fn assign_nearest(rgba: &RgbaImage, palette: &[[u8; 4]], options: &ColorOptions) -> Vec<Option<usize>> {
+    let (width, height) = rgba.dimensions();
+    let mut labels = vec![None; (width * height) as usize];
+    if palette.is_empty() {
+        return labels;
+    }
+    
+    let mut matcher = PaletteMatcher::new(palette, &options.metric);
+    // 误差扩散缓冲区（sRGB 分量）
+    let mut errors = vec![[0f32; 3]; (width * height) as usize];
+    // 有序抖动的幅度随调色板变稀疏而增大
+    let spread = 255.0 / (palette.len() as f32).cbrt();
+    
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = rgba.get_pixel(x, y);
+            // 只处理不透明的像素
+            if pixel[3] < options.alpha_threshold {
+                continue;
+            }
+            
+            let index = (y * width + x) as usize;
+            let rgb = [pixel[0], pixel[1], pixel[2]];
+            let label = match options.dither {
+                Dither::None => matcher.nearest(rgb),
+                Dither::Ordered => {
+                    let offset = ((BAYER_4X4[(y % 4) as usize][(x % 4) as usize] + 0.5) / 16.0 - 0.5) * spread;
+                    matcher.nearest(rgb.map(|c| (c as f32 + offset).round().clamp(0.0, 255.0) as u8))
+                }
+                Dither::FloydSteinberg => {
+                    let value = [0, 1, 2].map(|i| rgb[i] as f32 + errors[index][i]);
+                    let label = matcher.nearest(value.map(|c| c.round().clamp(0.0, 255.0) as u8));
+                    let error = [0, 1, 2].map(|i| value[i] - palette[label][i] as f32);
+                    
+                    // 将量化误差按 7/16、3/16、5/16、1/16 扩散到相邻像素
+                    for (dx, dy, weight) in [(1, 0, 7.0), (-1, 1, 3.0), (0, 1, 5.0), (1, 1, 1.0)] {
+                        let nx = x as i32 + dx;
+                        let ny = y as i32 + dy;
+                        if nx >= 0 && nx < width as i32 && ny < height as i32 {
+                            let target = &mut errors[(ny as u32 * width + nx as u32) as usize];
+                            for (t, e) in target.iter_mut().zip(error) {
+                                *t += e * weight / 16.0;
+                            }
+                        }
+                    }
+                    label
+                }
+            };
+            labels[index] = Some(label);
+        }
+    }
+    
+    labels
+}
+
+/// 带缓存的最近调色板颜色查找
+struct PaletteMatcher<'a> {
+    metric: &'a ColorMetric,
+    palette: Vec<[f32; 3]>,
+    cache: HashMap<[u8; 3], usize>,
+}
+
+impl<'a> PaletteMatcher<'a> {
+    fn new(palette: &[[u8; 4]], metric: &'a ColorMetric) -> Self {
+        PaletteMatcher {
+            metric,
+            palette: palette.iter().map(|c| metric.to_working([c[0], c[1], c[2]])).collect(),
+            cache: HashMap::new(),
+        }
+    }
+    
+    fn nearest(&mut self, rgb: [u8; 3]) -> usize {
+        let metric = self.metric;
+        let palette = &self.palette;
+        *self.cache.entry(rgb).or_insert_with(|| {
+            let point = metric.to_working(rgb);
+            palette.iter()
+                .enumerate()
+                .map(|(i, &c)| (i, metric.distance(point, c)))
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(i, _)| i)
+                .unwrap_or(0)
+        })
+    }
+}
+
+/// 根据逐像素的调色板下标构建互不重叠的图层
+fn layers_from_labels(rgba: &RgbaImage, colors: &[[u8; 4]], labels: &[Option<usize>]) -> Vec<(RgbaImage, [u8; 4])> {
+    let (width, height) = rgba.dimensions();
+    let mut layers: Vec<_> = colors.iter()
+        .map(|&color| (RgbaImage::new(width, height), color))
+        .collect();
+    
+    for (index, label) in labels.iter().enumerate() {
+        if let Some(label) = *label {
+            let x = index as u32 % width;
+            let y = index as u32 / width;
+            let color = colors[label];
+            // 使用调色板颜色，但保留透明度
+            let alpha = rgba.get_pixel(x, y)[3];
+            layers[label].0.put_pixel(x, y, Rgba([color[0], color[1], color[2], alpha]));
+        }
+    }
+    
+    layers
+}
+
+/// 按色差阈值构建图层，一个像素可能出现在多个图层中
+fn threshold_layers(rgba: &RgbaImage, colors: &[[u8; 4]], options: &ColorOptions) -> Vec<(RgbaImage, [u8; 4])> {
+    let (width, height) = rgba.dimensions();
+    
+    // 预先计算每个像素在工作空间中的坐标，避免每个图层重复转换
+    let mut cache = HashMap::new();
+    let working: Vec<[f32; 3]> = rgba.pixels()
+        .map(|p| {
+            let rgb = [p[0], p[1], p[2]];
+            *cache.entry(rgb).or_insert_with(|| options.metric.to_working(rgb))
+        })
+        .collect();
     
     // 为每个颜色创建一个图层
     let mut layers = Vec::new();
     
-    for &color in &colors {
+    for &color in colors {
         // 创建新的空白图层，所有像素初始化为透明
         let mut layer = RgbaImage::new(width, height);
+        let target = options.metric.to_working([color[0], color[1], color[2]]);
         
         // 复制原图中接近当前颜色的像素
         for y in 0..height {
             for x in 0..width {
                 let pixel = rgba.get_pixel(x, y);
                 // 只处理不透明的像素
-                if pixel[3] < 128 {
+                if pixel[3] < options.alpha_threshold {
                     continue;
                 }
                 
                 // 计算当前像素与目标颜色的距离
-                let distance = color_distance(pixel, color);
+                let index = (y * width + x) as usize;
+                let distance = options.metric.distance(working[index], target);
                 
                 // 如果距离小于阈值，则将此像素添加到当前图层
-                if distance < 60.0 { // 阈值可调整
+                if distance < options.threshold {
                     // 使用原始色彩，但保留透明度
                     let new_pixel = Rgba([color[0], color[1], color[2], pixel[3]]);
                     layer.put_pixel(x, y, new_pixel);
@@ -104,381 +621,234 @@ pub fn create_color_layers(img: &DynamicImage) -> Vec<(RgbaImage, [u8; 4])> {
             }
         }
         
+        // 图层之间可能重叠，小区域无法归并到某个邻色，只能删除
+        let mut labels: Vec<_> = layer.pixels().map(|p| (p[3] > 0).then_some(0)).collect();
+        remove_speckles(&mut labels, width, options.min_area, false);
+        for (pixel, label) in layer.pixels_mut().zip(labels) {
+            if label.is_none() {
+                *pixel = Rgba([0, 0, 0, 0]);
+            }
+        }
+        
         layers.push((layer, color));
     }
     
     layers
 }
 
-/// 量化颜色，将图像简化为较少的颜色
-pub fn quantize_colors(img: &RgbaImage, max_colors: usize) -> Vec<[u8; 4]> {
-    let (width, height) = img.dimensions();
-    let mut color_counts = HashMap::new();
+/// 将彩色图层转换为区域
+///
+/// 未设置 `options.simplify_tolerance` 时，由 `options.simplify` 简化级别换算像素容差。
+pub fn trace_layer(layer: &RgbaImage, options: &PathOptions) -> Vec<Region> {
+    let (width, height) = layer.dimensions();
     
-    // 对每个像素的颜色进行计数
-    for y in 0..height {
-        for x in 0..width {
-            let pixel = img.get_pixel(x, y);
-            // 忽略透明像素
-            if pixel[3] < 128 {
-                continue;
-            }
-            
-            // 简化颜色（量化为16位色深）
-            let simple_pixel = [
-                (pixel[0] / 16) * 16,
-                (pixel[1] / 16) * 16,
-                (pixel[2] / 16) * 16,
-                255
-            ];
-            
-            *color_counts.entry(simple_pixel).or_insert(0) += 1;
+    // 图层中的像素要么完全透明，要么属于该图层
+    let mut mask = GrayImage::new(width, height);
+    for (x, y, pixel) in layer.enumerate_pixels() {
+        if pixel[3] > 0 {
+            mask.put_pixel(x, y, Luma([255]));
         }
     }
     
-    // 将颜色按频率排序
-    let mut colors: Vec<_> = color_counts.into_iter().collect();
-    colors.sort_by(|a, b| b.1.cmp(&a.1)); // 按出现频率降序排序
-    
-    // 取出前max_colors个颜色
-    colors.iter().take(max_colors).map(|(color, _)| *color).collect()
-}
-
-/// 计算两个颜色之间的欧几里得距离
-pub fn color_distance(p1: &Rgba<u8>, p2: [u8; 4]) -> f32 {
-    let r1 = p1[0] as f32;
-    let g1 = p1[1] as f32;
-    let b1 = p1[2] as f32;
-    
-    let r2 = p2[0] as f32;
-    let g2 = p2[1] as f32;
-    let b2 = p2[2] as f32;
-    
-    // 计算欧几里得距离
-    ((r1 - r2).powi(2) + (g1 - g2).powi(2) + (b1 - b2).powi(2)).sqrt()
-}
-
-/// 将彩色图层转换为SVG路径
-pub fn trace_layer(layer: &RgbaImage) -> Vec<String> {
-    let (width, height) = layer.dimensions();
-    
-    // 将RGBA图层转换为灰度图用于边缘检测
-    let mut gray = GrayImage::new(width, height);
-    
-    // 添加填充效果，使图层边缘更平滑
-    let mut dilated = RgbaImage::new(width, height);
+    // 追踪区域边界（包括孔洞及其层级）
+    let mut contours = trace_contours(&mask);
     
-    // 先进行轻度膨胀操作，使边缘更连贯
-    let kernel_size = 2; // 膨胀核大小
-    for y in 0..height {
-        for x in 0..width {
-            let mut has_color = false;
-            
-            // 检查邻域是否有不透明像素
-            for dy in -kernel_size..=kernel_size {
-                for dx in -kernel_size..=kernel_size {
-                    let nx = x as i32 + dx;
-                    let ny = y as i32 + dy;
-                    
-                    if nx >= 0 && nx < width as i32 && ny >= 0 && ny < height as i32 {
-                        let pixel = layer.get_pixel(nx as u32, ny as u32);
-                        if pixel[3] > 128 { // 不透明像素
-                            has_color = true;
-                            break;
-                        }
-                    }
-                }
-                if has_color {
-                    break;
-                }
-            }
-            
-            if has_color {
-                dilated.put_pixel(x, y, *layer.get_pixel(x, y));
-            } else {
-                dilated.put_pixel(x, y, Rgba([0, 0, 0, 0]));
-            }
+    // 在点列上进行几何简化
+    let tolerance = options.simplify_tolerance.unwrap_or_else(|| level_to_tolerance(options.simplify));
+    for contour in &mut contours {
+        let simplified = simplify_closed(&contour.points, options.simplify_method, tolerance);
+        // 只有几个像素的轮廓可能被简化成面积不足一个像素的三角形，这时保留原轮廓
+        if simplified.len() > 2 && signed_area(&simplified).abs() as f64 >= MIN_REGION_AREA {
+            contour.points = simplified;
         }
     }
     
-    // 转换为灰度图
-    for y in 0..height {
-        for x in 0..width {
-            let pixel = dilated.get_pixel(x, y);
-            if pixel[3] > 128 { // 不透明像素
-                gray.put_pixel(x, y, Luma([255]));
-            } else {
-                gray.put_pixel(x, y, Luma([0]));
-            }
+    let to_subpath = |points: &[(f32, f32)]| {
+        if options.curves {
+            fit_contour(points, options.curve_tolerance as f64, options.corner_angle as f64)
+        } else {
+            SubPath::polygon(points)
         }
-    }
-    
-    // 平滑图像以减少过度细节
-    let blurred = imageproc::filter::gaussian_blur_f32(&gray, 0.8);
+    };
     
-    // 使用更合适的边缘检测参数
-    let edges = enhance_edges(&blurred, 10, 40);
-    
-    // 使用改进的轮廓追踪
-    let paths = trace_contours(&edges);
+    // 先按父轮廓将孔洞分组，避免为每个外轮廓扫描全部轮廓
+    let mut holes: Vec<Vec<&[(f32, f32)]>> = vec![Vec::new(); contours.len()];
+    for contour in contours.iter().filter(|c| c.is_hole && c.points.len() > 2) {
+        if let Some(parent) = contour.parent {
+            holes[parent].push(&contour.points);
+        }
+    }
     
-    // 对路径进行后处理，移除太小的路径
-    paths.into_iter()
-        .filter(|path| {
-            // 估算路径长度，忽略太短的路径
-            path.len() > 20
+    // 每个外轮廓与其孔洞组成一个区域
+    contours.iter()
+        .enumerate()
+        .filter(|(_, contour)| !contour.is_hole && contour.points.len() > 2)
+        .map(|(index, outer)| Region {
+            outer: to_subpath(&outer.points),
+            holes: holes[index].iter().map(|points| to_subpath(points)).collect(),
         })
-        .map(|path| path.to_string())
+        // 简化后面积不足一个像素的区域只是退化的残留
+        .filter(|region| region.area() >= MIN_REGION_AREA)
         .collect()
 }
 
-/// 增强图像边缘
-pub fn enhance_edges(img: &GrayImage, low_threshold: u8, high_threshold: u8) -> GrayImage {
-    let (width, height) = img.dimensions();
-    let mut result = GrayImage::new(width, height);
-    
-    // 简单的Sobel边缘检测
-    let sobel_x = [[-1, 0, 1], [-2, 0, 2], [-1, 0, 1]];
-    let sobel_y = [[-1, -2, -1], [0, 0, 0], [1, 2, 1]];
-    
-    for y in 1..height-1 {
-        for x in 1..width-1 {
-            let mut gx = 0;
-            let mut gy = 0;
-            
-            // 应用Sobel算子
-            for i in 0..3 {
-                for j in 0..3 {
-                    let img_x = x as i32 + (j as i32 - 1);
-                    let img_y = y as i32 + (i as i32 - 1);
-                    
-                    if img_x >= 0 && img_x < width as i32 && img_y >= 0 && img_y < height as i32 {
-                        let pixel = img.get_pixel(img_x as u32, img_y as u32)[0] as i32;
-                        gx += pixel * sobel_x[i as usize][j as usize];
-                        gy += pixel * sobel_y[i as usize][j as usize];
-                    }
-                }
-            }
-            
-            // 计算梯度大小
-            let g = ((gx.pow(2) + gy.pow(2)) as f32).sqrt();
-            
-            // 应用双阈值
-            if g >= high_threshold as f32 {
-                result.put_pixel(x, y, Luma([255]));
-            } else if g >= low_threshold as f32 {
-                result.put_pixel(x, y, Luma([128]));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::svg2png::{self, RenderOptions};
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+    const CLEAR: Rgba<u8> = Rgba([0, 0, 0, 0]);
+
+    /// 12×12：红色环（孔洞透明），孔洞中有一个蓝色小岛
+    fn ring_with_island() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(12, 12, |x, y| {
+            let within = |min: u32, max: u32| (min..max).contains(&x) && (min..max).contains(&y);
+            if within(5, 7) {
+                BLUE
+            } else if within(1, 11) && !within(3, 9) {
+                RED
             } else {
-                result.put_pixel(x, y, Luma([0]));
+                CLEAR
             }
+        }))
+    }
+
+    fn trace_options(fill_rule: FillRule) -> TraceOptions {
+        TraceOptions::builder()
+            .simplify(0)
+            .curves(false)
+            .fill_rule(fill_rule)
+            .build()
+    }
+
+    fn render(svg: &str) -> RgbaImage {
+        let png = svg2png::convert_svg_to_png(svg, &RenderOptions::default()).unwrap();
+        image::load_from_memory(&png).unwrap().to_rgba8()
+    }
+
+    #[test]
+    fn ring_region_keeps_its_hole() {
+        let image = trace_image(&ring_with_island(), &trace_options(FillRule::EvenOdd)).unwrap();
+        let red = image.layers.iter().find(|layer| layer.color == RED.0).unwrap();
+        assert_eq!(red.regions.len(), 1);
+        let region = &red.regions[0];
+        assert_eq!(region.holes.len(), 1);
+        assert_eq!(region.area(), 100.0 - 36.0);
+        // 孔洞方向与外轮廓相反，非零环绕规则依赖这一点
+        assert_ne!(region.outer.orientation(), region.holes[0].orientation());
+
+        let blue = image.layers.iter().find(|layer| layer.color == BLUE.0).unwrap();
+        assert_eq!(blue.regions.len(), 1);
+        assert!(blue.regions[0].holes.is_empty());
+        assert_eq!(blue.regions[0].area(), 4.0);
+    }
+
+    #[test]
+    fn even_odd_and_nonzero_render_the_same() {
+        let source = ring_with_island();
+        let mut rendered = Vec::new();
+        for fill_rule in [FillRule::EvenOdd, FillRule::NonZero] {
+            let svg = convert_png_to_svg(&source, &trace_options(fill_rule)).unwrap();
+            assert!(svg.contains(&format!("fill-rule=\"{}\"", fill_rule.as_str())));
+            let image = render(&svg);
+            assert_eq!(*image.get_pixel(2, 2), RED);
+            assert_eq!(image.get_pixel(4, 4)[3], 0, "{:?}下孔洞应保持透明", fill_rule);
+            assert_eq!(*image.get_pixel(6, 6), BLUE);
+            assert_eq!(image.get_pixel(0, 0)[3], 0);
+            rendered.push(image);
         }
+        assert_eq!(rendered[0], rendered[1]);
     }
-    
-    // 滞后阈值处理 - 连接边缘
-    let mut final_result = result.clone();
-    for y in 1..height-1 {
-        for x in 1..width-1 {
-            if result.get_pixel(x, y)[0] == 128 {
-                // 检查是否与强边缘相连
-                let mut is_connected = false;
-                for dy in -1..=1 {
-                    for dx in -1..=1 {
-                        if dx == 0 && dy == 0 {
-                            continue;
-                        }
-                        
-                        let nx = x as i32 + dx;
-                        let ny = y as i32 + dy;
-                        
-                        if nx >= 0 && nx < width as i32 && ny >= 0 && ny < height as i32 {
-                            if result.get_pixel(nx as u32, ny as u32)[0] == 255 {
-                                is_connected = true;
-                                break;
-                            }
-                        }
-                    }
-                    if is_connected {
-                        break;
-                    }
-                }
-                
-                if is_connected {
-                    final_result.put_pixel(x, y, Luma([255]));
-                } else {
-                    final_result.put_pixel(x, y, Luma([0]));
-                }
-            }
+
+    #[test]
+    fn one_pixel_wide_strip_is_traced() {
+        let strip = DynamicImage::ImageRgba8(RgbaImage::from_fn(8, 3, |x, y| {
+            if y == 1 && (1..7).contains(&x) { RED } else { CLEAR }
+        }));
+        let image = trace_image(&strip, &trace_options(FillRule::EvenOdd)).unwrap();
+        let regions: Vec<&Region> = image.layers.iter().flat_map(|layer| &layer.regions).collect();
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].area(), 6.0);
+        assert_eq!(regions[0].bbox(), kurbo::Rect::new(1.0, 1.0, 7.0, 2.0));
+    }
+
+    /// 在sRGB中比较，黑白两色的分界正好在灰度127.5处
+    const SRGB: ColorMetric = ColorMetric { space: ColorSpace::Srgb, delta_e: None };
+
+    /// 256×16的水平灰度渐变，以黑白两色的调色板分配后每列中白色像素的比例
+    fn white_fraction(dither: Dither) -> Vec<f32> {
+        let gradient = RgbaImage::from_fn(256, 16, |x, _| Rgba([x as u8, x as u8, x as u8, 255]));
+        let options = ColorOptions { dither, metric: SRGB, ..ColorOptions::default() };
+        let labels = assign_nearest(&gradient, &[[0, 0, 0, 255], [255, 255, 255, 255]], &options);
+        (0..256)
+            .map(|x| (0..16).filter(|y| labels[y * 256 + x] == Some(1)).count() as f32 / 16.0)
+            .collect()
+    }
+
+    /// 每32列的平均值
+    fn blocks(columns: &[f32]) -> Vec<f32> {
+        columns.chunks(32).map(|block| block.iter().sum::<f32>() / 32.0).collect()
+    }
+
+    #[test]
+    fn no_dither_is_a_hard_step() {
+        let fraction = white_fraction(Dither::None);
+        assert!(fraction[..128].iter().all(|&f| f == 0.0));
+        assert!(fraction[128..].iter().all(|&f| f == 1.0));
+    }
+
+    #[test]
+    fn floyd_steinberg_preserves_local_tone() {
+        let fraction = white_fraction(Dither::FloydSteinberg);
+        for (i, block) in blocks(&fraction).into_iter().enumerate() {
+            let gray = (i as f32 * 32.0 + 15.5) / 255.0;
+            assert!((block - gray).abs() < 0.08, "第{}块白色比例{}，灰度{}", i, block, gray);
         }
     }
-    
-    final_result
-}
 
-/// 从边缘图像跟踪轮廓并生成SVG路径
-pub fn trace_contours(edges: &GrayImage) -> Vec<String> {
-    let (width, height) = edges.dimensions();
-    let mut visited = vec![vec![false; width as usize]; height as usize];
-    let mut paths = Vec::new();
-    
-    // 查找起始点并跟踪轮廓
-    for y in 0..height {
-        for x in 0..width {
-            if edges.get_pixel(x, y)[0] == 255 && !visited[y as usize][x as usize] {
-                let mut contour = Vec::new();
-                let mut current_x = x;
-                let mut current_y = y;
-                
-                // 跟踪当前轮廓
-                loop {
-                    visited[current_y as usize][current_x as usize] = true;
-                    contour.push((current_x as f32, current_y as f32));
-                    
-                    // 查找下一个轮廓点
-                    let mut found_next = false;
-                    for dy in -1..=1 {
-                        for dx in -1..=1 {
-                            if dx == 0 && dy == 0 {
-                                continue;
-                            }
-                            
-                            let nx = current_x as i32 + dx;
-                            let ny = current_y as i32 + dy;
-                            
-                            if nx >= 0 && nx < width as i32 && ny >= 0 && ny < height as i32 {
-                                let nx = nx as u32;
-                                let ny = ny as u32;
-                                
-                                if edges.get_pixel(nx, ny)[0] == 255 && !visited[ny as usize][nx as usize] {
-                                    current_x = nx;
-                                    current_y = ny;
-                                    found_next = true;
-                                    break;
-                                }
-                            }
-                        }
-                        if found_next {
-                            break;
-                        }
-                    }
-                    
-                    if !found_next || contour.len() > 10000 { // 防止无限循环
-                        break;
-                    }
-                }
-                
-                // 创建SVG路径
-                if contour.len() > 2 {
-                    let mut path_data = String::new();
-                    path_data.push('M');
-                    path_data.push_str(&format!("{:.1},{:.1}", contour[0].0, contour[0].1));
-                    
-                    for i in 1..contour.len() {
-                        path_data.push_str(&format!(" L{:.1},{:.1}", contour[i].0, contour[i].1));
-                    }
-                    
-                    path_data.push('Z');
-                    paths.push(path_data);
-                }
+    #[test]
+    fn ordered_dither_mixes_midtones_with_a_repeating_pattern() {
+        let fraction = white_fraction(Dither::Ordered);
+        let blocks = blocks(&fraction);
+        assert!(blocks.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", blocks);
+        assert_eq!(blocks[0], 0.0);
+        assert_eq!(blocks[7], 1.0);
+        // 中间调中两种颜色交替出现，而不是在中点处截断
+        assert!(blocks[2..6].iter().all(|&block| block > 0.0 && block < 1.0), "{:?}", blocks);
+
+        let gray = RgbaImage::from_pixel(8, 8, Rgba([128, 128, 128, 255]));
+        let options = ColorOptions { dither: Dither::Ordered, metric: SRGB, ..ColorOptions::default() };
+        let labels = assign_nearest(&gray, &[[0, 0, 0, 255], [255, 255, 255, 255]], &options);
+        assert!(labels.contains(&Some(0)) && labels.contains(&Some(1)));
+        for y in 0..8 {
+            for x in 0..8 {
+                assert_eq!(labels[y * 8 + x], labels[(y % 4) * 8 + x % 4], "Bayer矩阵每4像素重复");
             }
         }
     }
-    
-    paths
-}
 
-/// 简化SVG路径，根据简化级别调整精度
-pub fn simplify_paths(paths: &[String], simplify_level: u8) -> Vec<String> {
-    if simplify_level == 0 {
-        // No simplification
-        return paths.to_vec();
+    #[test]
+    fn single_pixel_survives_default_options() {
+        let pixel = DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, RED));
+        let svg = convert_png_to_svg(&pixel, &TraceOptions::default()).unwrap();
+        assert!(svg.contains("<path"), "单个像素不应被丢弃: {}", svg);
+        assert_eq!(*render(&svg).get_pixel(0, 0), RED);
     }
-    
-    // Convert simplify_level (0-10) to a precision factor
-    // Higher simplify_level means lower precision (more simplification)
-    let precision = 10.0 - (simplify_level as f64 * 0.9); // Maps 1-10 to ~9.1-1.0
-    
-    paths.iter()
-        .map(|path| {
-            // Parse the SVG path and simplify it
-            let simplified_path = simplify_svg_path(path, precision);
-            simplified_path
-        })
-        .collect()
-}
 
-/// 根据精度简化SVG路径
-pub fn simplify_svg_path(path: &str, precision: f64) -> String {
-    // Split the path into commands (M, L, Z, etc.)
-    let mut result = String::new();
-    let mut prev_x = 0.0;
-    let mut prev_y = 0.0;
-    
-    // Process each command in the path
-    let mut chars = path.chars().peekable();
-    while let Some(c) = chars.next() {
-        match c {
-            'M' | 'L' => {
-                result.push(c);
-                // Extract the x and y coordinates
-                let mut x_str = String::new();
-                let mut y_str = String::new();
-                
-                // Skip whitespace
-                while chars.peek().map_or(false, |&c| c.is_whitespace()) {
-                    chars.next();
-                }
-                
-                // Get x coordinate
-                while chars.peek().map_or(false, |&c| c.is_digit(10) || c == '.' || c == '-') {
-                    x_str.push(chars.next().unwrap());
-                }
-                
-                // Skip whitespace or comma
-                while chars.peek().map_or(false, |&c| c.is_whitespace() || c == ',') {
-                    chars.next();
-                }
-                
-                // Get y coordinate
-                while chars.peek().map_or(false, |&c| c.is_digit(10) || c == '.' || c == '-') {
-                    y_str.push(chars.next().unwrap());
-                }
-                
-                // Parse and round the coordinates based on precision
-                if let (Ok(x), Ok(y)) = (x_str.parse::<f64>(), y_str.parse::<f64>()) {
-                    // Apply precision-based rounding
-                    let rounded_x = (x * precision).round() / precision;
-                    let rounded_y = (y * precision).round() / precision;
-                    
-                    // Only add the point if it's significantly different from the previous point
-                    let threshold = 1.0 / precision;
-                    if c == 'M' || 
-                       ((rounded_x - prev_x).abs() > threshold || 
-                        (rounded_y - prev_y).abs() > threshold) {
-                        let _ = write!(result, "{:.1},{:.1}", rounded_x, rounded_y);
-                        prev_x = rounded_x;
-                        prev_y = rounded_y;
-                    } else {
-                        // Skip this point as it's too close to the previous one
-                        continue;
-                    }
-                } else {
-                    // If parsing fails, just add the original coordinates
-                    result.push_str(&format!("{}{}", x_str, y_str));
-                }
-            },
-            'Z' => {
-                result.push('Z');
-            },
-            ' ' | ',' => {
-                // Skip extra whitespace and commas
-                continue;
-            },
-            _ => {
-                // Copy any other characters as is
-                result.push(c);
-            }
-        }
+    #[test]
+    fn checkerboard_survives_default_options() {
+        let board = DynamicImage::ImageRgba8(RgbaImage::from_fn(16, 16, |x, y| {
+            if (x + y) % 2 == 0 { RED } else { BLUE }
+        }));
+        let image = trace_image(&board, &TraceOptions::default()).unwrap();
+        let area: f64 = image.layers.iter().flat_map(|layer| &layer.regions).map(Region::area).sum();
+        assert!(area >= 0.9 * 256.0, "棋盘格只剩{}像素的面积", area);
+
+        let rendered = render(&convert_png_to_svg(&board, &TraceOptions::default()).unwrap());
+        assert_eq!(*rendered.get_pixel(0, 0), RED);
+        assert_eq!(*rendered.get_pixel(1, 0), BLUE);
+        assert_eq!(*rendered.get_pixel(8, 7), BLUE);
     }
-    
-    result
-} 
\ No newline at end of file
+}
@@ -0,0 +1,449 @@
+use super::error::{ConvertError, Result};
+use image::RgbaImage;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use super::color::ColorMetric;
+
+/// K-means 最大迭代次数
+const KMEANS_MAX_ITERATIONS: usize = 16;
+
+/// 八叉树最大深度（每个通道8位）
+const OCTREE_DEPTH: usize = 8;
+
+/// 颜色量化算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Quantizer {
+    /// 中位切分：反复沿跨度最大的通道在中位数处切分颜色盒
+    #[default]
+    MedianCut,
+    /// K-means 聚类，使用 k-means++ 选取初始中心
+    KMeans,
+    /// 八叉树：合并像素数最少的叶子直到颜色数不超过上限
+    Octree,
+}
+
+impl FromStr for Quantizer {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "median-cut" | "mediancut" | "median" => Ok(Quantizer::MedianCut),
+            "k-means" | "kmeans" => Ok(Quantizer::KMeans),
+            "octree" => Ok(Quantizer::Octree),
+            _ => Err(ConvertError::InvalidOption(format!("未知的量化算法: {}（可选: median-cut, k-means, octree）", s))),
+        }
+    }
+}
+
+impl Quantizer {
+    /// 从图像的不透明像素中提取最多 `max_colors` 种代表色，按覆盖像素数降序排列
+    ///
+    /// 不透明度低于 `alpha_threshold` 的像素视为透明，不参与量化。
+    /// 中位切分与 K-means 在 `metric` 的工作空间中进行；八叉树按 RGB 位平面划分，不受其影响。
+    pub fn quantize(&self, img: &RgbaImage, max_colors: usize, metric: &ColorMetric, alpha_threshold: u8) -> Vec<[u8; 4]> {
+        let histogram = build_histogram(img, alpha_threshold);
+        if histogram.is_empty() || max_colors == 0 {
+            return Vec::new();
+        }
+
+        let mut palette = match self {
+            Quantizer::MedianCut => median_cut(&histogram, max_colors, metric),
+            Quantizer::KMeans => kmeans(&histogram, max_colors, metric),
+            Quantizer::Octree => octree(&histogram, max_colors),
+        };
+
+        palette.sort_by_key(|&(_, n)| std::cmp::Reverse(n));
+        palette.into_iter()
+            .map(|(c, _)| [c[0], c[1], c[2], 255])
+            .collect()
+    }
+}
+
+/// 统计不透明像素的颜色直方图
+fn build_histogram(img: &RgbaImage, alpha_threshold: u8) -> Vec<([u8; 3], u64)> {
+    let mut counts: HashMap<[u8; 3], u64> = HashMap::new();
+    for pixel in img.pixels() {
+        if pixel[3] < alpha_threshold {
+            continue;
+        }
+        *counts.entry([pixel[0], pixel[1], pixel[2]]).or_insert(0) += 1;
+    }
+
+    let mut histogram: Vec<_> = counts.into_iter().collect();
+    // HashMap 的遍历顺序不固定，排序以保证结果可复现
+    histogram.sort_unstable();
+    histogram
+}
+
+/// 工作空间中的加权样本
+struct Sample {
+    point: [f32; 3],
+    weight: u64,
+}
+
+fn to_samples(histogram: &[([u8; 3], u64)], metric: &ColorMetric) -> Vec<Sample> {
+    histogram.iter()
+        .map(|&(c, n)| Sample { point: metric.to_working(c), weight: n })
+        .collect()
+}
+
+/// 计算一组样本在工作空间中的加权平均色
+fn weighted_mean(samples: &[Sample], metric: &ColorMetric) -> ([u8; 3], u64) {
+    let mut sum = [0f64; 3];
+    let mut total = 0u64;
+    for sample in samples {
+        for (s, &p) in sum.iter_mut().zip(&sample.point) {
+            *s += p as f64 * sample.weight as f64;
+        }
+        total += sample.weight;
+    }
+    let t = total.max(1) as f64;
+    let mean = [(sum[0] / t) as f32, (sum[1] / t) as f32, (sum[2] / t) as f32];
+    (metric.from_working(mean), total)
+}
+
+/// 中位切分量化
+fn median_cut(histogram: &[([u8; 3], u64)], max_colors: usize, metric: &ColorMetric) -> Vec<([u8; 3], u64)> {
+    let mut boxes: Vec<Vec<Sample>> = vec![to_samples(histogram, metric)];
+
+    while boxes.len() < max_colors {
+        // 选择 跨度×像素数 最大、且可再切分的颜色盒
+        let candidate = boxes.iter()
+            .enumerate()
+            .filter(|(_, b)| b.len() > 1)
+            .map(|(i, b)| {
+                let (axis, range) = widest_axis(b);
+                let count: u64 = b.iter().map(|s| s.weight).sum();
+                (i, axis, range as f64 * count as f64)
+            })
+            .filter(|&(_, _, score)| score > 0.0)
+            .max_by(|a, b| a.2.total_cmp(&b.2));
+
+        let Some((index, axis, _)) = candidate else {
+            break;
+        };
+
+        let mut samples = boxes.swap_remove(index);
+        samples.sort_unstable_by(|a, b| a.point[axis].total_cmp(&b.point[axis]));
+
+        // 按像素数寻找中位位置，保证两侧都非空
+        let total: u64 = samples.iter().map(|s| s.weight).sum();
+        let mut acc = 0u64;
+        let mut split = samples.len() / 2;
+        for (i, sample) in samples.iter().enumerate() {
+            acc += sample.weight;
+            if acc * 2 >= total {
+                split = i + 1;
+                break;
+            }
+        }
+        let split = split.clamp(1, samples.len() - 1);
+
+        let upper = samples.split_off(split);
+        boxes.push(samples);
+        boxes.push(upper);
+    }
+
+    boxes.iter().map(|b| weighted_mean(b, metric)).collect()
+}
+
+/// 返回颜色盒中跨度最大的坐标轴及其跨度
+fn widest_axis(samples: &[Sample]) -> (usize, f32) {
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for sample in samples {
+        for (axis, &p) in sample.point.iter().enumerate() {
+            min[axis] = min[axis].min(p);
+            max[axis] = max[axis].max(p);
+        }
+    }
+    (0..3)
+        .map(|axis| (axis, max[axis] - min[axis]))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or((0, 0.0))
+}
+
+/// 简单的 xorshift 伪随机数生成器，固定种子使量化结果可复现
+struct XorShift(u64);
+
+impl XorShift {
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+fn nearest(point: [f32; 3], centers: &[[f32; 3]], metric: &ColorMetric) -> usize {
+    centers.iter()
+        .enumerate()
+        .map(|(i, &c)| (i, metric.distance(point, c)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+/// K-means 量化，使用 k-means++ 初始化
+fn kmeans(histogram: &[([u8; 3], u64)], max_colors: usize, metric: &ColorMetric) -> Vec<([u8; 3], u64)> {
+    let samples = to_samples(histogram, metric);
+    let k = max_colors.min(samples.len());
+
+    // k-means++：第一个中心取像素数最多的颜色，其余按到最近中心距离平方加权抽样
+    let mut rng = XorShift(0x9E37_79B9_7F4A_7C15);
+    let first = samples.iter()
+        .enumerate()
+        .max_by_key(|(_, s)| s.weight)
+        .map(|(i, _)| i)
+        .unwrap_or(0);
+    let mut centers = vec![samples[first].point];
+    let mut nearest_sq: Vec<f64> = samples.iter()
+        .map(|s| (metric.distance(s.point, centers[0]) as f64).powi(2))
+        .collect();
+
+    while centers.len() < k {
+        let total: f64 = nearest_sq.iter().zip(&samples).map(|(&d, s)| d * s.weight as f64).sum();
+        if total <= 0.0 {
+            break;
+        }
+        let mut target = rng.next_f64() * total;
+        let mut chosen = samples.len() - 1;
+        for (i, (&d, s)) in nearest_sq.iter().zip(&samples).enumerate() {
+            target -= d * s.weight as f64;
+            if target <= 0.0 {
+                chosen = i;
+                break;
+            }
+        }
+        let center = samples[chosen].point;
+        centers.push(center);
+        for (d, s) in nearest_sq.iter_mut().zip(&samples) {
+            *d = d.min((metric.distance(s.point, center) as f64).powi(2));
+        }
+    }
+
+    // Lloyd 迭代
+    let mut assignment = vec![0usize; samples.len()];
+    for iteration in 0..KMEANS_MAX_ITERATIONS {
+        let mut changed = false;
+        for (slot, sample) in assignment.iter_mut().zip(&samples) {
+            let c = nearest(sample.point, &centers, metric);
+            if *slot != c {
+                *slot = c;
+                changed = true;
+            }
+        }
+        if !changed && iteration > 0 {
+            break;
+        }
+
+        let mut sums = vec![[0f64; 3]; centers.len()];
+        let mut totals = vec![0f64; centers.len()];
+        for (&c, sample) in assignment.iter().zip(&samples) {
+            for (s, &p) in sums[c].iter_mut().zip(&sample.point) {
+                *s += p as f64 * sample.weight as f64;
+            }
+            totals[c] += sample.weight as f64;
+        }
+        for (c, center) in centers.iter_mut().enumerate() {
+            if totals[c] > 0.0 {
+                *center = sums[c].map(|s| (s / totals[c]) as f32);
+            }
+        }
+    }
+
+    let mut counts = vec![0u64; centers.len()];
+    for (&c, sample) in assignment.iter().zip(&samples) {
+        counts[c] += sample.weight;
+    }
+
+    centers.iter()
+        .zip(counts)
+        .filter(|(_, n)| *n > 0)
+        .map(|(&c, n)| (metric.from_working(c), n))
+        .collect()
+}
+
+#[derive(Default)]
+struct OctreeNode {
+    /// 颜色分量之和，只在叶子中累计
+    sum: [u64; 3],
+    /// 子树中的像素数
+    count: u64,
+    children: [Option<usize>; 8],
+    is_leaf: bool,
+}
+
+/// 八叉树量化
+fn octree(histogram: &[([u8; 3], u64)], max_colors: usize) -> Vec<([u8; 3], u64)> {
+    let mut nodes = vec![OctreeNode::default()];
+    // 每一层中可被合并的内部节点
+    let mut levels: Vec<Vec<usize>> = vec![Vec::new(); OCTREE_DEPTH];
+    levels[0].push(0);
+    let mut leaf_count = 0usize;
+
+    for &(color, n) in histogram {
+        let mut node = 0;
+        nodes[0].count += n;
+        for level in 0..OCTREE_DEPTH {
+            let shift = 7 - level;
+            let index = (((color[0] >> shift) & 1) << 2
+                | ((color[1] >> shift) & 1) << 1
+                | ((color[2] >> shift) & 1)) as usize;
+            node = match nodes[node].children[index] {
+                Some(child) => child,
+                None => {
+                    let child = nodes.len();
+                    let is_leaf = level + 1 == OCTREE_DEPTH;
+                    nodes.push(OctreeNode { is_leaf, ..Default::default() });
+                    nodes[node].children[index] = Some(child);
+                    if is_leaf {
+                        leaf_count += 1;
+                    } else {
+                        levels[level + 1].push(child);
+                    }
+                    child
+                }
+            };
+            nodes[node].count += n;
+        }
+        let leaf = &mut nodes[node];
+        for (sum, &channel) in leaf.sum.iter_mut().zip(&color) {
+            *sum += channel as u64 * n;
+        }
+    }
+
+    // 从最深层开始，合并像素数最少的节点。更深的层合并完后本层节点的子节点都是叶子，
+    // 且合并不改变同层其他节点的像素数，因此每层只需排序一次
+    for level in (0..OCTREE_DEPTH).rev() {
+        if leaf_count <= max_colors {
+            break;
+        }
+        let mut candidates = std::mem::take(&mut levels[level]);
+        candidates.sort_by_key(|&i| std::cmp::Reverse(nodes[i].count));
+
+        while leaf_count > max_colors {
+            let Some(node) = candidates.pop() else {
+                break;
+            };
+
+            let mut sum = [0u64; 3];
+            let mut merged = 0usize;
+            for child in nodes[node].children.iter().flatten() {
+                for (sum, &channel) in sum.iter_mut().zip(&nodes[*child].sum) {
+                    *sum += channel;
+                }
+                merged += 1;
+            }
+
+            let target = &mut nodes[node];
+            target.sum = sum;
+            target.children = [None; 8];
+            target.is_leaf = true;
+            leaf_count = leaf_count + 1 - merged;
+        }
+    }
+
+    let mut palette = Vec::new();
+    let mut stack = vec![0usize];
+    while let Some(i) = stack.pop() {
+        let node = &nodes[i];
+        if node.is_leaf {
+            if node.count > 0 {
+                let rgb = node.sum.map(|s| (s / node.count) as u8);
+                palette.push((rgb, node.count));
+            }
+        } else {
+            stack.extend(node.children.iter().flatten());
+        }
+    }
+    palette
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::color::ColorSpace;
+    use image::Rgba;
+    use std::time::{Duration, Instant};
+
+    /// 每个像素颜色随机的图像，几乎每个像素都是不同的颜色
+    fn noise(width: u32, height: u32) -> RgbaImage {
+        let mut rng = XorShift(0x2545_F491_4F6C_DD1D);
+        RgbaImage::from_fn(width, height, |_, _| {
+            let value = (rng.next_f64() * (1u64 << 24) as f64) as u32;
+            Rgba([(value >> 16) as u8, (value >> 8) as u8, value as u8, 255])
+        })
+    }
+
+    /// 64×64的平滑渐变，共4096种颜色
+    fn gradient() -> RgbaImage {
+        RgbaImage::from_fn(64, 64, |x, y| Rgba([(x * 4) as u8, (y * 4) as u8, ((x + y) * 2) as u8, 255]))
+    }
+
+    const QUANTIZERS: [Quantizer; 3] = [Quantizer::MedianCut, Quantizer::KMeans, Quantizer::Octree];
+
+    #[test]
+    fn palette_size_is_bounded() {
+        let img = gradient();
+        for quantizer in QUANTIZERS {
+            for max_colors in [1, 2, 8, 16, 64] {
+                let palette = quantizer.quantize(&img, max_colors, &ColorMetric::default(), 128);
+                assert!(!palette.is_empty() && palette.len() <= max_colors, "{:?}: {}种颜色（上限{}）", quantizer, palette.len(), max_colors);
+                if quantizer != Quantizer::Octree {
+                    // 八叉树一次合并最多8个叶子，可能少于上限
+                    assert_eq!(palette.len(), max_colors, "{:?}", quantizer);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn quantizers_are_deterministic() {
+        let img = gradient();
+        for quantizer in QUANTIZERS {
+            for space in [ColorSpace::Srgb, ColorSpace::Lab, ColorSpace::Oklab] {
+                let metric = ColorMetric { space, delta_e: None };
+                let first = quantizer.quantize(&img, 12, &metric, 128);
+                assert_eq!(first, quantizer.quantize(&img, 12, &metric, 128), "{:?} / {:?}", quantizer, space);
+            }
+        }
+    }
+
+    #[test]
+    fn few_colors_are_kept_exactly() {
+        let colors = [[255, 0, 0], [0, 255, 0], [0, 0, 255]];
+        // 红色最多、蓝色最少，调色板按像素数降序排列
+        let img = RgbaImage::from_fn(6, 1, |x, _| {
+            let c = colors[[0, 0, 0, 1, 1, 2][x as usize]];
+            Rgba([c[0], c[1], c[2], 255])
+        });
+        for quantizer in QUANTIZERS {
+            let palette = quantizer.quantize(&img, 8, &ColorMetric::default(), 128);
+            assert_eq!(palette, [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255]], "{:?}", quantizer);
+        }
+    }
+
+    #[test]
+    fn transparent_pixels_are_ignored() {
+        let img = RgbaImage::from_fn(4, 1, |x, _| if x < 3 { Rgba([0, 0, 0, 0]) } else { Rgba([9, 9, 9, 255]) });
+        for quantizer in QUANTIZERS {
+            assert_eq!(quantizer.quantize(&img, 4, &ColorMetric::default(), 128), [[9, 9, 9, 255]]);
+        }
+        let clear = RgbaImage::from_pixel(2, 2, Rgba([0, 0, 0, 0]));
+        assert!(Quantizer::MedianCut.quantize(&clear, 4, &ColorMetric::default(), 128).is_empty());
+    }
+
+    #[test]
+    fn octree_reduces_noise_quickly() {
+        let img = noise(800, 800);
+        let start = Instant::now();
+        let palette = Quantizer::Octree.quantize(&img, 8, &ColorMetric::default(), 128);
+        let elapsed = start.elapsed();
+
+        assert!(!palette.is_empty() && palette.len() <= 8, "调色板有{}种颜色", palette.len());
+        // 逐个候选重新统计子树时需要几分钟
+        assert!(elapsed < Duration::from_secs(20), "八叉树量化用时{:?}", elapsed);
+    }
+}
@@ -0,0 +1,108 @@
+use super::error::{ConvertError, Result};
+use image::{AnimationDecoder, DynamicImage, ImageFormat};
+use image::codecs::gif::GifDecoder;
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+/// 可以矢量化的位图格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RasterFormat {
+    Png,
+    Jpeg,
+    /// GIF，动画可选择帧
+    Gif,
+    Bmp,
+    WebP,
+    Tiff,
+    /// ICO，取其中最大的图像
+    Ico,
+}
+
+impl RasterFormat {
+    /// 格式名称，用于提示信息
+    pub fn name(self) -> &'static str {
+        match self {
+            RasterFormat::Png => "PNG",
+            RasterFormat::Jpeg => "JPEG",
+            RasterFormat::Gif => "GIF",
+            RasterFormat::Bmp => "BMP",
+            RasterFormat::WebP => "WebP",
+            RasterFormat::Tiff => "TIFF",
+            RasterFormat::Ico => "ICO",
+        }
+    }
+
+    fn image_format(self) -> ImageFormat {
+        match self {
+            RasterFormat::Png => ImageFormat::Png,
+            RasterFormat::Jpeg => ImageFormat::Jpeg,
+            RasterFormat::Gif => ImageFormat::Gif,
+            RasterFormat::Bmp => ImageFormat::Bmp,
+            RasterFormat::WebP => ImageFormat::WebP,
+            RasterFormat::Tiff => ImageFormat::Tiff,
+            RasterFormat::Ico => ImageFormat::Ico,
+        }
+    }
+}
+
+/// 支持的格式列表，用于错误提示
+const SUPPORTED: &str = "PNG, JPEG, GIF, BMP, WebP, TIFF, ICO";
+
+/// 根据文件头识别图像格式，不依赖文件扩展名
+pub fn detect_format(data: &[u8]) -> Result<RasterFormat> {
+    let format = image::guess_format(data)
+        .map_err(|_| ConvertError::UnsupportedFormat(format!("无法识别的图像格式（支持: {}）", SUPPORTED)))?;
+    match format {
+        ImageFormat::Png => Ok(RasterFormat::Png),
+        ImageFormat::Jpeg => Ok(RasterFormat::Jpeg),
+        ImageFormat::Gif => Ok(RasterFormat::Gif),
+        ImageFormat::Bmp => Ok(RasterFormat::Bmp),
+        ImageFormat::WebP => Ok(RasterFormat::WebP),
+        ImageFormat::Tiff => Ok(RasterFormat::Tiff),
+        ImageFormat::Ico => Ok(RasterFormat::Ico),
+        other => Err(ConvertError::UnsupportedFormat(format!("不支持的图像格式: {:?}（支持: {}）", other, SUPPORTED))),
+    }
+}
+
+/// 识别格式并解码位图
+///
+/// `frame` 为GIF动画的帧序号（从0开始），省略时取第一帧；其他格式只接受 `None` 或 0。
+pub fn decode_raster(data: &[u8], frame: Option<usize>) -> Result<DynamicImage> {
+    let format = detect_format(data)?;
+
+    if format == RasterFormat::Gif {
+        let index = frame.unwrap_or(0);
+        let decoder = GifDecoder::new(Cursor::new(data))
+            .map_err(|e| ConvertError::Decode(format!("GIF解码错误: {}", e)))?;
+        let mut count = 0;
+        for decoded in decoder.into_frames() {
+            let decoded = decoded.map_err(|e| ConvertError::Decode(format!("GIF解码错误: {}", e)))?;
+            if count == index {
+                return Ok(DynamicImage::ImageRgba8(decoded.into_buffer()));
+            }
+            count += 1;
+        }
+        return Err(ConvertError::InvalidOption(format!("GIF只有{}帧，无法选择第{}帧（从0开始）", count, index)));
+    }
+
+    if frame.is_some_and(|index| index > 0) {
+        return Err(ConvertError::InvalidOption(format!("{}图像只有一帧，只有GIF支持选择帧", format.name())));
+    }
+    image::load_from_memory_with_format(data, format.image_format())
+        .map_err(|e| ConvertError::Decode(format!("{}解码错误: {}", format.name(), e)))
+}
+
+/// 只读取文件头中的位图宽高，不解码像素
+pub fn raster_dimensions(data: &[u8], format: RasterFormat) -> Result<(u32, u32)> {
+    image::io::Reader::with_format(Cursor::new(data), format.image_format())
+        .into_dimensions()
+        .map_err(|e| ConvertError::Decode(format!("{}解码错误: {}", format.name(), e)))
+}
+
+/// 读取并解码位图文件，格式由文件内容识别
+pub fn load_raster_file(path: impl AsRef<Path>, frame: Option<usize>) -> Result<DynamicImage> {
+    let path = path.as_ref();
+    let data = fs::read(path).map_err(|e| ConvertError::io(format!("无法读取 {}", path.display()), e))?;
+    decode_raster(&data, frame)
+}
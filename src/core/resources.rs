@@ -0,0 +1,311 @@
+use super::error::{ConvertError, Result};
+use flate2::read::GzDecoder;
+use resvg::usvg::{self, ImageHrefResolver, ImageKind, TreeParsing};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use super::limits::{LimitError, Limits};
+use super::raster::{self, RasterFormat};
+
+/// 对SVG中引用的外部文件（`<image href="...">`）的访问策略
+///
+/// data URL与 `ResourceOptions::data` 中的资源不受影响。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExternalPolicy {
+    /// 允许读取任意本地文件，相对路径相对于资源目录（未设置时相对于当前目录）
+    Allow,
+    /// 只允许读取资源目录中的文件
+    #[default]
+    ResourcesDir,
+    /// 不读取任何本地文件
+    Deny,
+}
+
+impl FromStr for ExternalPolicy {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "allow" => Ok(ExternalPolicy::Allow),
+            "resources" | "resources-dir" => Ok(ExternalPolicy::ResourcesDir),
+            "deny" => Ok(ExternalPolicy::Deny),
+            _ => Err(ConvertError::InvalidOption(format!("未知的外部资源策略: {}（可选: allow, resources, deny）", s))),
+        }
+    }
+}
+
+/// SVG中 `<image>` 引用的资源来源
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResourceOptions {
+    /// 解析相对路径的目录；文件接口未设置时使用输入文件所在目录
+    pub resources_dir: Option<PathBuf>,
+    /// 外部文件的访问策略
+    pub policy: ExternalPolicy,
+    /// 内存中的资源，键为SVG中 `href` 的原文，优先于文件系统
+    pub data: HashMap<String, Arc<Vec<u8>>>,
+    /// 引用图像的上限：位图按文件头中的宽高检查像素数，内嵌SVG检查结构；安全模式下由 `RenderOptions::limits` 设置
+    pub limits: Option<Limits>,
+}
+
+impl ResourceOptions {
+    /// 未设置资源目录时使用 `input_path` 所在的目录
+    pub fn with_input_dir(&self, input_path: &Path) -> ResourceOptions {
+        let mut options = self.clone();
+        if options.resources_dir.is_none() {
+            let dir = input_path.parent().unwrap_or(Path::new(""));
+            options.resources_dir = Some(if dir.as_os_str().is_empty() { PathBuf::from(".") } else { dir.to_path_buf() });
+        }
+        options
+    }
+}
+
+/// 解析期间因超出上限而被拒绝的引用图像
+///
+/// 解析器只能返回 `Option`，超出上限的图像先被跳过并记录在这里，由调用方在解析结束后报告。
+#[derive(Debug, Clone, Default)]
+pub struct ImageRejection(Arc<Mutex<Option<LimitError>>>);
+
+impl ImageRejection {
+    /// 只保留第一个错误
+    fn record(&self, error: LimitError) {
+        if let Ok(mut slot) = self.0.lock() {
+            slot.get_or_insert(error);
+        }
+    }
+
+    /// 取出记录的错误
+    pub fn take(&self) -> Option<LimitError> {
+        self.0.lock().ok().and_then(|mut slot| slot.take())
+    }
+}
+
+/// 按选项构建 `<image>` 的 `href` 解析器
+///
+/// 除usvg原生支持的PNG、JPEG、GIF和SVG外，WebP、BMP、TIFF等位图会先解码再转换为PNG。
+/// 被引用的SVG中的 `<image>` 不会被加载。超出 `options.limits` 的图像被跳过并记录到 `rejection`。
+pub fn href_resolver(options: &ResourceOptions, rejection: &ImageRejection) -> ImageHrefResolver {
+    let data = options.data.clone();
+    let policy = options.policy;
+    let dir = options.resources_dir.clone();
+    let limits = options.limits;
+    let data_rejection = rejection.clone();
+    let string_rejection = rejection.clone();
+
+    ImageHrefResolver {
+        resolve_data: Box::new(move |mime, data, opts| {
+            image_kind(&data, mime == "image/svg+xml", limits.as_ref(), &data_rejection, opts)
+        }),
+        resolve_string: Box::new(move |href, opts| {
+            if let Some(bytes) = data.get(href) {
+                return image_kind(bytes, href_is_svg(href), limits.as_ref(), &string_rejection, opts);
+            }
+            let path = external_path(href, policy, dir.as_deref())?;
+            let bytes = Arc::new(fs::read(&path).ok()?);
+            image_kind(&bytes, href_is_svg(href), limits.as_ref(), &string_rejection, opts)
+        }),
+    }
+}
+
+/// 按访问策略确定 `href` 对应的本地文件，不允许访问时返回 `None`
+fn external_path(href: &str, policy: ExternalPolicy, dir: Option<&Path>) -> Option<PathBuf> {
+    let href = href.strip_prefix("file://").unwrap_or(href);
+    // 其他协议（http等）不会被请求
+    if href.contains("://") {
+        return None;
+    }
+    let path = Path::new(href);
+    match policy {
+        ExternalPolicy::Deny => None,
+        ExternalPolicy::Allow => Some(dir.map_or_else(|| path.to_path_buf(), |dir| dir.join(path))),
+        ExternalPolicy::ResourcesDir => {
+            // 规范化后比较，防止通过 `..` 或符号链接访问目录外的文件
+            let dir = dir?.canonicalize().ok()?;
+            let path = dir.join(path).canonicalize().ok()?;
+            path.starts_with(&dir).then_some(path)
+        }
+    }
+}
+
+fn href_is_svg(href: &str) -> bool {
+    let href = href.to_ascii_lowercase();
+    href.ends_with(".svg") || href.ends_with(".svgz")
+}
+
+/// 按内容识别资源类型，usvg不支持的位图格式转换为PNG
+fn image_kind(
+    data: &Arc<Vec<u8>>,
+    is_svg: bool,
+    limits: Option<&Limits>,
+    rejection: &ImageRejection,
+    opts: &usvg::Options,
+) -> Option<ImageKind> {
+    if !is_svg {
+        if let Ok(format) = raster::detect_format(data) {
+            if let Some(limits) = limits {
+                // 解码前按文件头中的宽高检查，防止小文件声明巨大的尺寸
+                let (width, height) = raster::raster_dimensions(data, format).ok()?;
+                if let Err(error) = limits.check_pixels(width, height) {
+                    return reject(error, rejection);
+                }
+            }
+            return match format {
+                RasterFormat::Png => Some(ImageKind::PNG(data.clone())),
+                RasterFormat::Jpeg => Some(ImageKind::JPEG(data.clone())),
+                RasterFormat::Gif => Some(ImageKind::GIF(data.clone())),
+                _ => {
+                    let image = raster::decode_raster(data, None).ok()?;
+                    let mut png = Vec::new();
+                    image.write_to(&mut Cursor::new(&mut png), image::ImageOutputFormat::Png).ok()?;
+                    Some(ImageKind::PNG(Arc::new(png)))
+                }
+            };
+        }
+    }
+    load_sub_svg(data, limits, rejection, opts)
+}
+
+/// 记录超出上限的错误并跳过该图像，其他错误只跳过图像
+fn reject(error: ConvertError, rejection: &ImageRejection) -> Option<ImageKind> {
+    if let ConvertError::LimitExceeded(error) = error {
+        rejection.record(error);
+    }
+    None
+}
+
+/// 检查被引用的SVG，gzip压缩的数据最多解压到 `max_input_bytes` 多一个字节
+fn check_sub_svg(data: &[u8], limits: &Limits) -> Result<Vec<u8>> {
+    let data = if data.starts_with(&[0x1f, 0x8b]) {
+        let mut decoded = Vec::new();
+        GzDecoder::new(data)
+            .take(limits.max_input_bytes as u64 + 1)
+            .read_to_end(&mut decoded)
+            .map_err(|e| ConvertError::ParseSvg(format!("无法解压SVG: {}", e)))?;
+        decoded
+    } else {
+        data.to_vec()
+    };
+    let svg_data = std::str::from_utf8(&data).map_err(|_| ConvertError::ParseSvg("SVG不是有效的UTF-8".to_string()))?;
+    limits.check_svg(svg_data)?;
+    Ok(data)
+}
+
+/// 加载被引用的SVG，其中的 `<image>` 一律忽略
+fn load_sub_svg(data: &[u8], limits: Option<&Limits>, rejection: &ImageRejection, opts: &usvg::Options) -> Option<ImageKind> {
+    let data = match limits {
+        Some(limits) => match check_sub_svg(data, limits) {
+            Ok(data) => Cow::Owned(data),
+            Err(error) => return reject(error, rejection),
+        },
+        None => Cow::Borrowed(data),
+    };
+    let sub_options = usvg::Options {
+        resources_dir: None,
+        image_href_resolver: ImageHrefResolver {
+            resolve_data: Box::new(|_, _, _| None),
+            resolve_string: Box::new(|_, _| None),
+        },
+        font_family: opts.font_family.clone(),
+        languages: opts.languages.clone(),
+        ..*opts
+    };
+    usvg::Tree::from_data(&data, &sub_options).ok().map(ImageKind::SVG)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::fonts::FontOptions;
+    use crate::core::svg2png::parse_svg;
+    use base64::Engine;
+
+    fn secure() -> ResourceOptions {
+        ResourceOptions { policy: ExternalPolicy::Deny, limits: Some(Limits::default()), ..Default::default() }
+    }
+
+    /// 用data URL内嵌 `data` 的SVG
+    fn embed(mime: &str, data: &[u8]) -> String {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(data);
+        format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><image width="10" height="10" href="data:{};base64,{}"/></svg>"#,
+            mime, encoded
+        )
+    }
+
+    fn limit_error(svg: &str, resources: &ResourceOptions) -> LimitError {
+        match parse_svg(svg, &FontOptions::default(), resources) {
+            Err(ConvertError::LimitExceeded(error)) => error,
+            other => panic!("应超出上限，实际为 {:?}", other.map(|_| ())),
+        }
+    }
+
+    fn png_chunk(png: &mut Vec<u8>, kind: &[u8], data: &[u8]) {
+        let mut crc = flate2::Crc::new();
+        crc.update(kind);
+        crc.update(data);
+        png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        png.extend_from_slice(kind);
+        png.extend_from_slice(data);
+        png.extend_from_slice(&crc.sum().to_be_bytes());
+    }
+
+    /// IHDR声明 `width`×`height`，但只有几个字节像素数据的PNG
+    fn png_header(width: u32, height: u32) -> Vec<u8> {
+        let mut ihdr = width.to_be_bytes().to_vec();
+        ihdr.extend_from_slice(&height.to_be_bytes());
+        ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        png_chunk(&mut png, b"IHDR", &ihdr);
+        png_chunk(&mut png, b"IDAT", &[0x78, 0x9c, 0x03, 0x00]);
+        png_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    #[test]
+    fn oversized_raster_is_rejected_from_header() {
+        let svg = embed("image/png", &png_header(20000, 20000));
+        let error = limit_error(&svg, &secure());
+        assert_eq!(error, LimitError::TooManyPixels { width: 20000, height: 20000, max: Limits::default().max_pixels });
+    }
+
+    #[test]
+    fn small_raster_is_accepted() {
+        let mut png = Vec::new();
+        image::RgbaImage::new(4, 4)
+            .write_to(&mut Cursor::new(&mut png), image::ImageOutputFormat::Png)
+            .unwrap();
+        assert!(parse_svg(&embed("image/png", &png), &FontOptions::default(), &secure()).is_ok());
+    }
+
+    #[test]
+    fn nested_svg_is_checked() {
+        let dtd = r#"<?xml version="1.0"?><!DOCTYPE svg [<!ENTITY a "aaaa">]><svg xmlns="http://www.w3.org/2000/svg"/>"#;
+        assert_eq!(limit_error(&embed("image/svg+xml", dtd.as_bytes()), &secure()), LimitError::DtdNotAllowed);
+
+        let limits = Limits { max_depth: 4, ..Limits::default() };
+        let deep = format!(r#"<svg xmlns="http://www.w3.org/2000/svg">{}{}</svg>"#, "<g>".repeat(10), "</g>".repeat(10));
+        let resources = ResourceOptions { limits: Some(limits), ..secure() };
+        assert_eq!(limit_error(&embed("image/svg+xml", deep.as_bytes()), &resources), LimitError::NestingTooDeep { max: 4 });
+    }
+
+    #[test]
+    fn compressed_nested_svg_is_bounded() {
+        let limits = Limits { max_input_bytes: 1024, ..Limits::default() };
+        let svg = format!(r#"<svg xmlns="http://www.w3.org/2000/svg"><!--{}--></svg>"#, " ".repeat(4096));
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, svg.as_bytes()).unwrap();
+        let svgz = encoder.finish().unwrap();
+        assert!(svgz.len() < 1024);
+
+        let resources = ResourceOptions { limits: Some(limits), ..secure() };
+        assert_eq!(
+            limit_error(&embed("image/svg+xml", &svgz), &resources),
+            LimitError::InputTooLarge { size: 1025, max: 1024 }
+        );
+    }
+}
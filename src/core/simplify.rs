@@ -0,0 +1,255 @@
+use super::error::{ConvertError, Result};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::str::FromStr;
+
+/// 简化级别（0-10）每级对应的容差（像素）
+const TOLERANCE_PER_LEVEL: f32 = 0.25;
+
+/// 几何路径简化算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SimplifyMethod {
+    /// Ramer–Douglas–Peucker：删除到简化后折线距离小于容差的点
+    #[default]
+    DouglasPeucker,
+    /// Visvalingam–Whyatt：反复删除与相邻点构成三角形面积最小的点
+    Visvalingam,
+}
+
+impl FromStr for SimplifyMethod {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "rdp" | "douglas-peucker" => Ok(SimplifyMethod::DouglasPeucker),
+            "visvalingam" | "vw" => Ok(SimplifyMethod::Visvalingam),
+            _ => Err(ConvertError::InvalidOption(format!("未知的简化算法: {}（可选: rdp, visvalingam）", s))),
+        }
+    }
+}
+
+/// 将 0-10 的简化级别换算为像素容差
+pub fn level_to_tolerance(level: u8) -> f32 {
+    level.min(10) as f32 * TOLERANCE_PER_LEVEL
+}
+
+/// 简化闭合多边形，结果至少保留三个顶点
+///
+/// 对 Visvalingam 算法，容差换算为面积阈值 `tolerance²`。
+pub fn simplify_closed(points: &[(f32, f32)], method: SimplifyMethod, tolerance: f32) -> Vec<(f32, f32)> {
+    if tolerance <= 0.0 || points.len() <= 3 {
+        return points.to_vec();
+    }
+
+    match method {
+        SimplifyMethod::DouglasPeucker => douglas_peucker_closed(points, tolerance),
+        SimplifyMethod::Visvalingam => visvalingam_closed(points, tolerance * tolerance),
+    }
+}
+
+/// 闭合多边形的 RDP：在起点与离起点最远的点处拆成两条折线分别简化
+fn douglas_peucker_closed(points: &[(f32, f32)], tolerance: f32) -> Vec<(f32, f32)> {
+    let n = points.len();
+    let far = (1..n)
+        .max_by(|&a, &b| distance_sq(points[0], points[a]).total_cmp(&distance_sq(points[0], points[b])))
+        .unwrap_or(n / 2);
+
+    let mut keep = vec![false; n];
+    keep[0] = true;
+    keep[far] = true;
+
+    let ring: Vec<usize> = (0..=n).map(|i| i % n).collect();
+    douglas_peucker(points, &ring[..=far], tolerance, &mut keep);
+    douglas_peucker(points, &ring[far..], tolerance, &mut keep);
+
+    let mut result: Vec<_> = (0..n).filter(|&i| keep[i]).map(|i| points[i]).collect();
+    if result.len() < 3 {
+        // 细长的形状可能只剩两个点，补回离弦线最远的点
+        let extra = (0..n)
+            .filter(|&i| !keep[i])
+            .max_by(|&a, &b| {
+                segment_distance(points[a], points[0], points[far])
+                    .total_cmp(&segment_distance(points[b], points[0], points[far]))
+            });
+        if let Some(extra) = extra {
+            keep[extra] = true;
+            result = (0..n).filter(|&i| keep[i]).map(|i| points[i]).collect();
+        }
+    }
+    result
+}
+
+/// 对下标序列表示的折线执行 RDP（使用显式栈避免深递归）
+fn douglas_peucker(points: &[(f32, f32)], indices: &[usize], tolerance: f32, keep: &mut [bool]) {
+    let mut stack = vec![(0, indices.len() - 1)];
+    while let Some((first, last)) = stack.pop() {
+        if last <= first + 1 {
+            continue;
+        }
+        let a = points[indices[first]];
+        let b = points[indices[last]];
+        let (index, distance) = (first + 1..last)
+            .map(|i| (i, segment_distance(points[indices[i]], a, b)))
+            .max_by(|x, y| x.1.total_cmp(&y.1))
+            .unwrap_or((first, 0.0));
+
+        if distance > tolerance {
+            keep[indices[index]] = true;
+            stack.push((first, index));
+            stack.push((index, last));
+        }
+    }
+}
+
+/// 堆中的候选点，按有效面积从小到大弹出
+#[derive(PartialEq)]
+struct Candidate {
+    area: f32,
+    index: usize,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.area.total_cmp(&self.area).then_with(|| other.index.cmp(&self.index))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// 闭合多边形的 Visvalingam–Whyatt 简化
+fn visvalingam_closed(points: &[(f32, f32)], min_area: f32) -> Vec<(f32, f32)> {
+    let n = points.len();
+    let mut prev: Vec<usize> = (0..n).map(|i| (i + n - 1) % n).collect();
+    let mut next: Vec<usize> = (0..n).map(|i| (i + 1) % n).collect();
+    let mut removed = vec![false; n];
+    let mut areas: Vec<f32> = (0..n)
+        .map(|i| triangle_area(points[prev[i]], points[i], points[next[i]]))
+        .collect();
+
+    let mut heap: BinaryHeap<Candidate> = areas.iter()
+        .enumerate()
+        .map(|(index, &area)| Candidate { area, index })
+        .collect();
+    let mut remaining = n;
+
+    while let Some(Candidate { area, index }) = heap.pop() {
+        // 跳过已删除或面积已更新的过期条目
+        if removed[index] || area != areas[index] {
+            continue;
+        }
+        if area >= min_area || remaining <= 3 {
+            break;
+        }
+
+        removed[index] = true;
+        remaining -= 1;
+        let (p, q) = (prev[index], next[index]);
+        next[p] = q;
+        prev[q] = p;
+
+        // 更新相邻点的面积；面积不小于被删除点，保证删除顺序单调
+        for neighbor in [p, q] {
+            let updated = triangle_area(points[prev[neighbor]], points[neighbor], points[next[neighbor]]).max(area);
+            areas[neighbor] = updated;
+            heap.push(Candidate { area: updated, index: neighbor });
+        }
+    }
+
+    (0..n).filter(|&i| !removed[i]).map(|i| points[i]).collect()
+}
+
+fn distance_sq(a: (f32, f32), b: (f32, f32)) -> f32 {
+    (a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)
+}
+
+/// 点到线段的距离
+fn segment_distance(p: (f32, f32), a: (f32, f32), b: (f32, f32)) -> f32 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_sq = dx * dx + dy * dy;
+    if length_sq == 0.0 {
+        return distance_sq(p, a).sqrt();
+    }
+    let t = (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length_sq).clamp(0.0, 1.0);
+    distance_sq(p, (a.0 + t * dx, a.1 + t * dy)).sqrt()
+}
+
+fn triangle_area(a: (f32, f32), b: (f32, f32), c: (f32, f32)) -> f32 {
+    ((b.0 - a.0) * (c.1 - a.1) - (c.0 - a.0) * (b.1 - a.1)).abs() / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METHODS: [SimplifyMethod; 2] = [SimplifyMethod::DouglasPeucker, SimplifyMethod::Visvalingam];
+
+    /// 10×10的正方形，每条边上每隔1像素一个共线点
+    fn dense_square() -> Vec<(f32, f32)> {
+        (0..40)
+            .map(|i| {
+                let t = (i % 10) as f32;
+                match i / 10 {
+                    0 => (t, 0.0),
+                    1 => (10.0, t),
+                    2 => (10.0 - t, 10.0),
+                    _ => (0.0, 10.0 - t),
+                }
+            })
+            .collect()
+    }
+
+    /// 顶边中点处有一个高 `height` 的尖顶的正方形
+    fn house(height: f32) -> Vec<(f32, f32)> {
+        vec![(0.0, 0.0), (5.0, -height), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]
+    }
+
+    #[test]
+    fn collinear_points_are_removed() {
+        for method in METHODS {
+            let simplified = simplify_closed(&dense_square(), method, 0.25);
+            assert_eq!(simplified, [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)], "{:?}", method);
+        }
+    }
+
+    #[test]
+    fn douglas_peucker_keeps_points_beyond_the_tolerance() {
+        let method = SimplifyMethod::DouglasPeucker;
+        assert_eq!(simplify_closed(&house(0.4), method, 0.5).len(), 4);
+        assert_eq!(simplify_closed(&house(0.6), method, 0.5), house(0.6));
+    }
+
+    #[test]
+    fn visvalingam_uses_the_squared_tolerance_as_area() {
+        // 尖顶三角形的面积为 10 × 高 / 2
+        let method = SimplifyMethod::Visvalingam;
+        assert_eq!(simplify_closed(&house(0.1), method, 1.0).len(), 4);
+        assert_eq!(simplify_closed(&house(0.1), method, 0.5), house(0.1));
+    }
+
+    #[test]
+    fn at_least_a_triangle_remains() {
+        let sliver = [(0.0, 0.0), (5.0, 0.01), (10.0, 0.0), (5.0, -0.01)];
+        for method in METHODS {
+            let simplified = simplify_closed(&sliver, method, 5.0);
+            assert_eq!(simplified.len(), 3, "{:?}", method);
+            // 结果是原顶点按原顺序的子序列
+            assert!(simplified.iter().all(|p| sliver.contains(p)));
+        }
+    }
+
+    #[test]
+    fn zero_tolerance_is_a_no_op() {
+        for method in METHODS {
+            assert_eq!(simplify_closed(&dense_square(), method, 0.0), dense_square());
+        }
+        assert_eq!(level_to_tolerance(0), 0.0);
+        assert_eq!(level_to_tolerance(3), 0.75);
+        assert_eq!(level_to_tolerance(20), level_to_tolerance(10));
+    }
+}
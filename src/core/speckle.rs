@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+
+/// 合并后新形成的小区域最多再处理的轮数
+const MAX_PASSES: usize = 4;
+
+/// 消除逐像素标签图中面积小于 `min_area` 像素的连通区域
+///
+/// `merge` 为真时，小区域改为与其共享边界最长的相邻标签（主导邻色），
+/// 没有不透明邻居的孤立小区域以及 `merge` 为假时的小区域直接置为透明。
+/// 按面积从小到大处理，合并后仍然过小的区域在下一轮继续处理。
+pub fn remove_speckles(labels: &mut [Option<usize>], width: u32, min_area: u32, merge: bool) {
+    if min_area <= 1 || width == 0 {
+        return;
+    }
+    let width = width as usize;
+
+    for _ in 0..MAX_PASSES {
+        let (owner, mut components) = label_components(labels, width);
+        components.retain(|pixels| pixels.len() < min_area as usize);
+        if components.is_empty() {
+            break;
+        }
+        components.sort_by_key(Vec::len);
+
+        let mut changed = false;
+        for pixels in &components {
+            let target = if merge {
+                dominant_neighbor(labels, &owner, pixels, width)
+            } else {
+                None
+            };
+            for &index in pixels {
+                labels[index] = target;
+            }
+            changed |= target.is_some();
+        }
+
+        // 只有合并才可能产生新的小区域，删除一轮即可完成
+        if !changed {
+            break;
+        }
+    }
+}
+
+/// 统计区域外侧相邻像素的标签，返回出现次数最多者（次数相同时取调色板下标小的）
+fn dominant_neighbor(
+    labels: &[Option<usize>],
+    owner: &[usize],
+    pixels: &[usize],
+    width: usize,
+) -> Option<usize> {
+    let height = labels.len() / width;
+    let id = owner[pixels[0]];
+    let mut counts: HashMap<usize, usize> = HashMap::new();
+    for &index in pixels {
+        for n in neighbors(index, width, height).into_iter().flatten() {
+            if owner[n] == id {
+                continue;
+            }
+            if let Some(label) = labels[n] {
+                *counts.entry(label).or_default() += 1;
+            }
+        }
+    }
+    counts.into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+        .map(|(label, _)| label)
+}
+
+/// 以4连通方式标记同一标签的连通区域
+///
+/// 返回逐像素的区域编号（透明像素为 `usize::MAX`）以及每个区域包含的像素下标。
+fn label_components(labels: &[Option<usize>], width: usize) -> (Vec<usize>, Vec<Vec<usize>>) {
+    let height = labels.len() / width;
+    let mut owner = vec![usize::MAX; labels.len()];
+    let mut components = Vec::new();
+    let mut stack = Vec::new();
+
+    for start in 0..labels.len() {
+        let Some(label) = labels[start] else { continue };
+        if owner[start] != usize::MAX {
+            continue;
+        }
+        let id = components.len();
+        let mut pixels = vec![start];
+        owner[start] = id;
+        stack.push(start);
+        while let Some(index) = stack.pop() {
+            for n in neighbors(index, width, height).into_iter().flatten() {
+                if owner[n] == usize::MAX && labels[n] == Some(label) {
+                    owner[n] = id;
+                    pixels.push(n);
+                    stack.push(n);
+                }
+            }
+        }
+        components.push(pixels);
+    }
+
+    (owner, components)
+}
+
+fn neighbors(index: usize, width: usize, height: usize) -> [Option<usize>; 4] {
+    let (x, y) = (index % width, index / width);
+    [
+        (x > 0).then(|| index - 1),
+        (x + 1 < width).then(|| index + 1),
+        (y > 0).then(|| index - width),
+        (y + 1 < height).then(|| index + width),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 由字符画构造标签图：数字为调色板下标，`.` 为透明
+    fn grid(rows: &[&str]) -> (Vec<Option<usize>>, u32) {
+        let labels = rows.iter()
+            .flat_map(|row| row.chars())
+            .map(|c| c.to_digit(10).map(|d| d as usize))
+            .collect();
+        (labels, rows[0].len() as u32)
+    }
+
+    #[test]
+    fn speckle_merges_into_dominant_neighbor() {
+        let (mut labels, width) = grid(&[
+            "00000",
+            "21122",
+            "22222",
+        ]);
+        // 与2共享4条边，与0共享2条边
+        remove_speckles(&mut labels, width, 3, true);
+        assert_eq!(labels, grid(&["00000", "22222", "22222"]).0);
+    }
+
+    #[test]
+    fn ties_go_to_the_lower_palette_index() {
+        // 中心像素与0、3各共享两条边，两种朝向都选择0
+        for rows in [["000", "013", "333"], ["333", "310", "000"]] {
+            let (mut labels, width) = grid(&rows);
+            remove_speckles(&mut labels, width, 2, true);
+            assert_eq!(labels[4], Some(0), "{:?}", rows);
+        }
+    }
+
+    #[test]
+    fn speckles_are_cleared_without_merging() {
+        let (mut labels, width) = grid(&[
+            "0000",
+            "0100",
+            "0000",
+        ]);
+        remove_speckles(&mut labels, width, 2, false);
+        assert_eq!(labels, grid(&["0000", "0.00", "0000"]).0);
+    }
+
+    #[test]
+    fn isolated_speckle_becomes_transparent() {
+        let (mut labels, width) = grid(&[
+            "....",
+            ".1..",
+            "...0",
+        ]);
+        remove_speckles(&mut labels, width, 2, true);
+        assert_eq!(labels, grid(&["....", "....", "...."]).0);
+    }
+
+    #[test]
+    fn large_regions_are_untouched() {
+        let rows = ["0011", "0011", "2233"];
+        let (mut labels, width) = grid(&rows);
+        remove_speckles(&mut labels, width, 2, true);
+        assert_eq!(labels, grid(&rows).0);
+        // min_area 为 0 或 1 时不做任何处理
+        let (mut single, width) = grid(&["010"]);
+        remove_speckles(&mut single, width, 1, true);
+        assert_eq!(single, grid(&["010"]).0);
+    }
+}
@@ -1,107 +1,1304 @@
-use anyhow::{Result, anyhow};
-use resvg::usvg::{self, TreeParsing};
+use super::error::{ConvertError, Result};
+use resvg::usvg::{self, TreeParsing, TreeTextToPath, TreeWriting};
 use resvg::tiny_skia;
+use image::ImageEncoder;
 use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-/// 将SVG转换为PNG图像数据
-pub fn convert_svg_to_png(
-    svg_data: &str,
-    width: u32,
-    height: u32,
-) -> Result<Vec<u8>> {
-    // 配置SVG解析选项
-    let mut opt = usvg::Options::default();
-    opt.font_family = "Arial, Helvetica, sans-serif".to_string();
-    opt.font_size = 16.0;
-    opt.languages = vec!["zh-CN".to_string(), "en".to_string()]; // 支持中文和英文
-    opt.shape_rendering = usvg::ShapeRendering::GeometricPrecision;
-    opt.text_rendering = usvg::TextRendering::GeometricPrecision;
-    opt.image_rendering = usvg::ImageRendering::OptimizeQuality;
-    
+use super::fonts::{self, FontOptions};
+use super::limits::{LimitError, Limits};
+use super::resources::{self, ExternalPolicy, ImageRejection, ResourceOptions};
+
+/// SVG 用户单位（CSS 像素）对应的分辨率
+const CSS_DPI: f32 = 96.0;
+
+/// 棋盘格背景的格子边长（像素）
+const CHECKER_SIZE: u32 = 8;
+
+/// 棋盘格背景的两种颜色
+const CHECKER_COLORS: [[u8; 4]; 2] = [[255, 255, 255, 255], [204, 204, 204, 255]];
+
+/// 渲染未直接显示的元素（如 `<symbol>`）时注入的 `<use>` 元素的id前缀
+const TARGET_ID_PREFIX: &str = "svg-converter-render-target";
+
+/// JPEG的默认质量
+const DEFAULT_JPEG_QUALITY: u8 = 90;
+
+/// 同时指定宽度和高度时，图像与输出框宽高比不一致的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FitMode {
+    /// 分别缩放两个方向以填满输出框，可能变形
+    Stretch,
+    /// 等比缩放至完整放入输出框，居中并在两侧留出透明边
+    #[default]
+    Contain,
+    /// 等比缩放至完全覆盖输出框，居中并裁掉超出部分
+    Cover,
+}
+
+impl FromStr for FitMode {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "stretch" | "fill" => Ok(FitMode::Stretch),
+            "contain" => Ok(FitMode::Contain),
+            "cover" => Ok(FitMode::Cover),
+            _ => Err(ConvertError::InvalidOption(format!("未知的适配方式: {}（可选: stretch, contain, cover）", s))),
+        }
+    }
+}
+
+/// 渲染时位于SVG下方的背景
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Background {
+    /// 透明背景
+    #[default]
+    Transparent,
+    /// 纯色背景（RGBA）
+    Color([u8; 4]),
+    /// 灰白棋盘格，用于预览透明区域
+    Checkerboard,
+}
+
+impl FromStr for Background {
+    type Err = ConvertError;
+
+    /// 接受 `transparent`、`checkerboard` 或任意CSS颜色（如 `white`、`#1e90ff`、`rgba(0,0,0,0.5)`）
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "transparent" | "none" => Ok(Background::Transparent),
+            "checkerboard" | "checker" => Ok(Background::Checkerboard),
+            _ => {
+                let color = svgtypes::Color::from_str(s.trim())
+                    .map_err(|_| ConvertError::InvalidOption(format!("无法识别的背景: {}（可选: transparent, checkerboard 或CSS颜色）", s)))?;
+                Ok(Background::Color([color.red, color.green, color.blue, color.alpha]))
+            }
+        }
+    }
+}
+
+/// viewBox 坐标系中的矩形区域
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Area {
+    /// 左上角 x 坐标
+    pub x: f32,
+    /// 左上角 y 坐标
+    pub y: f32,
+    /// 宽度
+    pub width: f32,
+    /// 高度
+    pub height: f32,
+}
+
+impl FromStr for Area {
+    type Err = ConvertError;
+
+    /// 解析 `x,y,width,height`（也可用空格分隔）
+    fn from_str(s: &str) -> Result<Self> {
+        let values = s.split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|part| !part.is_empty())
+            .map(|part| part.parse::<f32>())
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|_| ConvertError::InvalidOption(format!("无法解析区域: {}（格式: x,y,width,height）", s)))?;
+        match values[..] {
+            [x, y, width, height] if width > 0.0 && height > 0.0 => Ok(Area { x, y, width, height }),
+            [_, _, _, _] => Err(ConvertError::InvalidOption(format!("区域的宽高必须大于0: {}", s))),
+            _ => Err(ConvertError::InvalidOption(format!("无法解析区域: {}（格式: x,y,width,height）", s))),
+        }
+    }
+}
+
+/// 输出图像格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// PNG（默认）
+    #[default]
+    Png,
+    /// JPEG，有损压缩，不支持透明通道
+    Jpeg,
+    /// WebP，无损压缩
+    WebP,
+    /// TIFF
+    Tiff,
+    /// BMP
+    Bmp,
+    /// QOI
+    Qoi,
+}
+
+impl FromStr for OutputFormat {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "png" => Ok(OutputFormat::Png),
+            "jpeg" | "jpg" => Ok(OutputFormat::Jpeg),
+            "webp" => Ok(OutputFormat::WebP),
+            "tiff" | "tif" => Ok(OutputFormat::Tiff),
+            "bmp" => Ok(OutputFormat::Bmp),
+            "qoi" => Ok(OutputFormat::Qoi),
+            _ => Err(ConvertError::InvalidOption(format!("未知的输出格式: {}（可选: png, jpeg, webp, tiff, bmp, qoi）", s))),
+        }
+    }
+}
+
+impl OutputFormat {
+    /// 根据文件扩展名推断输出格式
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?;
+        extension.parse().ok()
+    }
+
+    /// 对应的MIME类型
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            OutputFormat::Png => "image/png",
+            OutputFormat::Jpeg => "image/jpeg",
+            OutputFormat::WebP => "image/webp",
+            OutputFormat::Tiff => "image/tiff",
+            OutputFormat::Bmp => "image/bmp",
+            OutputFormat::Qoi => "image/qoi",
+        }
+    }
+
+    /// 是否能保存透明通道
+    pub fn supports_alpha(self) -> bool {
+        self != OutputFormat::Jpeg
+    }
+}
+
+/// PNG颜色类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PngColor {
+    /// 真彩色：RGBA，去掉透明通道时为RGB（默认）
+    #[default]
+    Truecolor,
+    /// 灰度，带透明时为灰度+透明通道
+    Grayscale,
+    /// 索引色：量化为调色板，透明度保存在tRNS块中
+    Palette,
+}
+
+impl FromStr for PngColor {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "truecolor" | "rgb" | "rgba" => Ok(PngColor::Truecolor),
+            "grayscale" | "gray" | "grey" => Ok(PngColor::Grayscale),
+            "palette" | "indexed" => Ok(PngColor::Palette),
+            _ => Err(ConvertError::InvalidOption(format!("未知的PNG颜色类型: {}（可选: truecolor, grayscale, palette）", s))),
+        }
+    }
+}
+
+/// PNG压缩级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PngCompression {
+    /// 快速压缩，文件较大
+    Fast,
+    /// 默认压缩
+    #[default]
+    Default,
+    /// 最高压缩，编码较慢
+    Best,
+}
+
+impl FromStr for PngCompression {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "fast" => Ok(PngCompression::Fast),
+            "default" => Ok(PngCompression::Default),
+            "best" => Ok(PngCompression::Best),
+            _ => Err(ConvertError::InvalidOption(format!("未知的压缩级别: {}（可选: fast, default, best）", s))),
+        }
+    }
+}
+
+/// PNG编码选项
+#[derive(Debug, Clone, PartialEq)]
+pub struct PngOptions {
+    /// 颜色类型
+    pub color: PngColor,
+    /// 索引色的最大颜色数（1-256），颜色更多时进行量化
+    pub palette_colors: u16,
+    /// 每个通道的位深，8或16；索引色只支持8位（颜色少时自动使用更低的位深）
+    pub bit_depth: u8,
+    /// 压缩级别
+    pub compression: PngCompression,
+    /// 写入pHYs块，记录输出像素对应的分辨率（见 `output_dpi`），打印时保持SVG的物理尺寸
+    pub write_dpi: bool,
+    /// 写入的文本元数据（关键字, 内容），非Latin-1内容写入iTXt块
+    pub text: Vec<(String, String)>,
+}
+
+impl Default for PngOptions {
+    fn default() -> Self {
+        PngOptions {
+            color: PngColor::default(),
+            palette_colors: 256,
+            bit_depth: 8,
+            compression: PngCompression::default(),
+            write_dpi: false,
+            text: Vec::new(),
+        }
+    }
+}
+
+/// 描述本转换器与源文件的文本元数据，可加入 `PngOptions::text`
+pub fn converter_text(source: Option<&str>) -> Vec<(String, String)> {
+    let mut text = vec![(
+        "Software".to_string(),
+        format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+    )];
+    if let Some(source) = source {
+        text.push(("Source".to_string(), source.to_string()));
+    }
+    text
+}
+
+/// 只渲染SVG的一部分
+#[derive(Debug, Clone, PartialEq)]
+pub enum Crop {
+    /// 指定id的元素（包括 `<symbol>` 和 `<defs>` 中的元素），裁剪到含描边的包围盒，
+    /// 四周留出 `padding`（SVG用户单位）
+    Element { id: String, padding: f32 },
+    /// viewBox 坐标系中的矩形
+    Area(Area),
+}
+
+/// SVG 渲染选项
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderOptions {
+    /// 输出宽度（像素）；只设置宽度时高度按宽高比计算，`Some(0)` 与 `None` 相同
+    pub width: Option<u32>,
+    /// 输出高度（像素）；只设置高度时宽度按宽高比计算，`Some(0)` 与 `None` 相同
+    pub height: Option<u32>,
+    /// 缩放倍数，仅在未指定宽高时作用于原始尺寸
+    pub zoom: f32,
+    /// 输出分辨率，仅在未指定宽高时生效；SVG 中 1px 按 96 DPI 换算
+    pub dpi: f32,
+    /// 同时指定宽高时的适配方式
+    pub fit: FitMode,
+    /// 背景，填满整个输出（包括 `FitMode::Contain` 留出的边距）
+    pub background: Background,
+    /// 将结果合成到白色上并去掉透明通道，输出不透明的RGB图像
+    pub flatten: bool,
+    /// 只渲染指定的元素或区域，输出的原始尺寸为裁剪后的大小
+    pub crop: Option<Crop>,
+    /// 输出图像格式；不支持透明通道的格式总是合成到白色上
+    pub format: OutputFormat,
+    /// JPEG质量（1-100），其他格式忽略
+    pub quality: u8,
+    /// PNG编码选项，其他格式忽略
+    pub png: PngOptions,
+    /// 渲染文本所用的字体
+    pub fonts: FontOptions,
+    /// `<image>` 引用的外部资源
+    pub resources: ResourceOptions,
+    /// 资源上限，设置后进入安全模式：超出上限时返回 `LimitError`，且不读取外部文件
+    pub limits: Option<Limits>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            width: None,
+            height: None,
+            zoom: 1.0,
+            dpi: CSS_DPI,
+            fit: FitMode::default(),
+            background: Background::default(),
+            flatten: false,
+            crop: None,
+            format: OutputFormat::default(),
+            quality: DEFAULT_JPEG_QUALITY,
+            png: PngOptions::default(),
+            fonts: FontOptions::default(),
+            resources: ResourceOptions::default(),
+            limits: None,
+        }
+    }
+}
+
+impl RenderOptions {
+    /// 用于不可信SVG的安全模式选项：默认资源上限，不读取外部文件
+    pub fn secure() -> Self {
+        RenderOptions {
+            resources: ResourceOptions { policy: ExternalPolicy::Deny, ..Default::default() },
+            limits: Some(Limits::default()),
+            ..Default::default()
+        }
+    }
+
+    /// 指定的输出宽高，0视为未指定
+    fn output_size(&self) -> (Option<u32>, Option<u32>) {
+        (self.width.filter(|&width| width > 0), self.height.filter(|&height| height > 0))
+    }
+
+    /// 实际使用的资源选项：安全模式下禁止读取外部文件
+    fn effective_resources(&self) -> ResourceOptions {
+        secure_resources(&self.resources, self.limits.as_ref())
+    }
+}
+
+/// 设置了 `limits`（安全模式）时禁止读取外部文件，引用的图像也受同样的上限约束
+fn secure_resources(resources: &ResourceOptions, limits: Option<&Limits>) -> ResourceOptions {
+    match limits {
+        Some(limits) => ResourceOptions { policy: ExternalPolicy::Deny, limits: Some(*limits), ..resources.clone() },
+        None => resources.clone(),
+    }
+}
+
+/// 批量渲染中的一张图像
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedImage {
+    /// 精灵图中的图标id，单个SVG渲染时为 `None`
+    pub id: Option<String>,
+    /// 缩放倍数：未指定宽高时为 `RenderOptions::zoom` 与所请求倍数之积，否则为所请求倍数
+    pub scale: f32,
+    /// 按 `RenderOptions::format` 编码的图像数据
+    pub data: Vec<u8>,
+}
+
+/// 文件转换的结果
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileReport {
+    /// 写入的文件路径
+    pub written: Vec<PathBuf>,
+    /// SVG文本引用但找不到的字体族（见 `missing_fonts`），相应文本使用后备字体
+    pub missing_fonts: Vec<String>,
+    /// 导出精灵图时因没有可渲染的内容（如空分组或 `display:none`）而跳过的图标id
+    pub skipped: Vec<String>,
+}
+
+/// 将SVG转换为图像数据，默认为PNG，其他格式由 `options.format` 指定
+pub fn convert_svg_to_png(svg_data: &str, options: &RenderOptions) -> Result<Vec<u8>> {
     // 解析SVG
-    let tree = usvg::Tree::from_str(svg_data, &opt)?;
+    let tree = parse_input(svg_data, options)?;
     
-    // 获取原始尺寸
-    let orig_size = tree.view_box.rect.size();
+    render_and_encode(&tree, options)
+}
+
+/// 渲染已解析的SVG并按输出格式编码
+fn render_and_encode(tree: &usvg::Tree, options: &RenderOptions) -> Result<Vec<u8>> {
+    // 渲染到像素缓冲区
+    let pixmap = render_tree(tree, options)?;
     
-    // 确定输出尺寸
-    let (width_final, height_final) = if width == 0 || height == 0 {
-        // 如果宽度或高度为0，使用原始SVG的尺寸
-        (orig_size.width() as u32, orig_size.height() as u32)
-    } else {
-        // 否则使用指定的尺寸
-        (width, height)
+    // 将像素缓冲区编码为输出格式
+    encode_image(&pixmap, output_dpi(tree.size, options)?, options)
+}
+
+/// 将SVG按多个缩放倍数渲染为图像，只解析一次，结果按 `scales` 的顺序排列
+///
+/// 倍数作用于 `options` 确定的尺寸：指定了宽高时按比例放大宽高，否则与 `options.zoom` 相乘。
+pub fn convert_svg_to_png_scales(svg_data: &str, scales: &[f32], options: &RenderOptions) -> Result<Vec<RenderedImage>> {
+    let tree = parse_input(svg_data, options)?;
+    render_scales(&tree, None, scales, options)
+}
+
+/// 按多个缩放倍数渲染同一棵SVG树并按输出格式编码
+fn render_scales(tree: &usvg::Tree, id: Option<&str>, scales: &[f32], options: &RenderOptions) -> Result<Vec<RenderedImage>> {
+    scales.iter()
+        .map(|&scale| {
+            if scale <= 0.0 || !scale.is_finite() {
+                return Err(ConvertError::InvalidDimensions(format!("缩放倍数必须大于0: {}", scale)));
+            }
+            let scale_length = |length: u32| ((length as f32 * scale).round() as u32).max(1);
+            let (width, height) = options.output_size();
+            let scaled = RenderOptions {
+                width: width.map(scale_length),
+                height: height.map(scale_length),
+                zoom: options.zoom * scale,
+                crop: None,
+                ..options.clone()
+            };
+            // 指定宽高时zoom不参与布局，不计入倍数
+            let sized = width.is_some() || height.is_some();
+            Ok(RenderedImage {
+                id: id.map(str::to_string),
+                scale: if sized { scale } else { scaled.zoom },
+                data: encode_image(&render_tree(tree, &scaled)?, output_dpi(tree.size, &scaled)?, options)?,
+            })
+        })
+        .collect()
+}
+
+/// 按 `options` 检查并解析SVG数据，再按 `options.crop` 裁剪
+///
+/// 设置了 `options.limits` 时先检查资源上限，且不读取外部文件。
+pub fn parse_input(svg_data: &str, options: &RenderOptions) -> Result<usvg::Tree> {
+    parse_input_with_missing_fonts(svg_data, options).map(|(tree, _)| tree)
+}
+
+/// 与 `parse_input` 相同，同时返回解析时找不到的字体族（见 `missing_fonts`）
+pub fn parse_input_with_missing_fonts(svg_data: &str, options: &RenderOptions) -> Result<(usvg::Tree, Vec<String>)> {
+    if let Some(limits) = &options.limits {
+        limits.check_svg(svg_data)?;
+    }
+    let resources = options.effective_resources();
+    let (tree, missing) = parse_svg_with_missing_fonts(svg_data, &options.fonts, &resources)?;
+    let tree = match &options.crop {
+        Some(crop) => crop_tree(svg_data, &tree, crop, &options.fonts, &resources)?,
+        None => tree,
+    };
+    Ok((tree, missing))
+}
+
+/// 解析SVG数据，按 `resources` 加载引用的图像，并用 `fonts` 中的字体将文本转换为路径
+pub fn parse_svg(svg_data: &str, fonts: &FontOptions, resources: &ResourceOptions) -> Result<usvg::Tree> {
+    parse_svg_with_missing_fonts(svg_data, fonts, resources).map(|(tree, _)| tree)
+}
+
+/// 解析SVG数据，并在文本转换为路径之前列出找不到的字体族
+fn parse_svg_with_missing_fonts(
+    svg_data: &str,
+    fonts: &FontOptions,
+    resources: &ResourceOptions,
+) -> Result<(usvg::Tree, Vec<String>)> {
+    let db = fonts::load_fonts(fonts)?;
+    let rejection = ImageRejection::default();
+    let mut tree = usvg::Tree::from_str(svg_data, &parse_options(fonts, resources, &rejection))?;
+    if let Some(error) = rejection.take() {
+        return Err(error.into());
+    }
+    let missing = fonts::missing_families(&tree, &db);
+    tree.convert_text(&db);
+    Ok((tree, missing))
+}
+
+/// 列出SVG文本引用但 `fonts` 中找不到的字体族，这些文本将使用后备字体渲染
+pub fn missing_fonts(svg_data: &str, fonts: &FontOptions) -> Result<Vec<String>> {
+    let db = fonts::load_fonts(fonts)?;
+    // 只关心文本，不加载外部图像
+    let resources = ResourceOptions { policy: ExternalPolicy::Deny, ..Default::default() };
+    let tree = usvg::Tree::from_str(svg_data, &parse_options(fonts, &resources, &ImageRejection::default()))?;
+    Ok(fonts::missing_families(&tree, &db))
+}
+
+/// SVG解析选项
+fn parse_options(fonts: &FontOptions, resources: &ResourceOptions, rejection: &ImageRejection) -> usvg::Options {
+    usvg::Options {
+        resources_dir: resources.resources_dir.clone(),
+        font_family: fonts.default_family.clone(),
+        font_size: 16.0,
+        languages: vec!["zh-CN".to_string(), "en".to_string()], // 支持中文和英文
+        shape_rendering: usvg::ShapeRendering::GeometricPrecision,
+        text_rendering: usvg::TextRendering::GeometricPrecision,
+        image_rendering: usvg::ImageRendering::OptimizeQuality,
+        image_href_resolver: resources::href_resolver(resources, rejection),
+        ..Default::default()
+    }
+}
+
+/// 构造只包含指定元素或区域的SVG树
+///
+/// `svg_data` 为 `tree` 的源数据，用于渲染不在渲染树中的元素（如 `<symbol>`）。
+/// 裁剪后的尺寸保持原SVG中 viewBox 到输出尺寸的缩放比例。
+pub fn crop_tree(
+    svg_data: &str,
+    tree: &usvg::Tree,
+    crop: &Crop,
+    fonts: &FontOptions,
+    resources: &ResourceOptions,
+) -> Result<usvg::Tree> {
+    match crop {
+        Crop::Area(area) => with_view_box(tree, *area),
+        Crop::Element { id, padding } => {
+            if let Some(node) = tree.node_by_id(id) {
+                return isolate_node(tree, &node, id, *padding);
+            }
+            // 不直接渲染的元素，通过注入 `<use>` 引用它
+            let tree = parse_svg(&inject_uses(svg_data, &[id.as_str()])?, fonts, resources)?;
+            let node = tree.node_by_id(&target_id(0))
+                .ok_or_else(|| ConvertError::ElementNotFound(format!("元素 {} 无法渲染", id)))?;
+            isolate_node(&tree, &node, id, *padding)
+        }
+    }
+}
+
+/// 只保留一个节点（保持其在画布中的位置），并裁剪到它的包围盒
+fn isolate_node(tree: &usvg::Tree, node: &usvg::Node, id: &str, padding: f32) -> Result<usvg::Tree> {
+    isolate_content(tree, node, padding)?
+        .ok_or_else(|| ConvertError::ElementNotFound(format!("元素 {} 没有可渲染的内容", id)))
+}
+
+/// 与 `isolate_node` 相同，节点没有可渲染的内容时返回 `None`
+fn isolate_content(tree: &usvg::Tree, node: &usvg::Node, padding: f32) -> Result<Option<usvg::Tree>> {
+    use usvg::NodeExt;
+    
+    // 用一个分组承载祖先节点的变换
+    let parent_transform = node.parent().map(|parent| parent.abs_transform()).unwrap_or_default();
+    let wrapper = usvg::Node::new(usvg::NodeKind::Group(usvg::Group {
+        transform: parent_transform,
+        ..Default::default()
+    }));
+    wrapper.append(node.make_deep_copy());
+    let root = usvg::Node::new(usvg::NodeKind::Group(usvg::Group::default()));
+    root.append(wrapper);
+    let isolated = usvg::Tree { size: tree.size, view_box: tree.view_box, root };
+    
+    // 包含描边与滤镜范围的包围盒
+    let Some(bbox) = resvg::Tree::from_usvg(&isolated).content_area else {
+        return Ok(None);
     };
+    with_view_box(&isolated, Area {
+        x: bbox.x() - padding,
+        y: bbox.y() - padding,
+        width: bbox.width() + 2.0 * padding,
+        height: bbox.height() + 2.0 * padding,
+    })
+    .map(Some)
+}
+
+/// 将 viewBox 替换为给定区域，并按原有缩放比例调整SVG尺寸
+fn with_view_box(tree: &usvg::Tree, area: Area) -> Result<usvg::Tree> {
+    let rect = usvg::NonZeroRect::from_xywh(area.x, area.y, area.width, area.height)
+        .ok_or_else(|| ConvertError::InvalidDimensions("区域的宽高必须大于0".to_string()))?;
+    let scale = usvg::utils::view_box_to_transform(tree.view_box.rect, tree.view_box.aspect, tree.size);
+    let size = usvg::Size::from_wh(area.width * scale.sx, area.height * scale.sy)
+        .ok_or_else(|| ConvertError::InvalidDimensions("区域的宽高必须大于0".to_string()))?;
+    
+    let mut cropped = tree.clone();
+    cropped.view_box = usvg::ViewBox { rect, aspect: usvg::AspectRatio::default() };
+    cropped.size = size;
+    Ok(cropped)
+}
+
+/// 在根元素末尾为每个id插入一个引用它的 `<use>`，第 i 个的id为 `target_id(i)`
+fn inject_uses(svg_data: &str, ids: &[&str]) -> Result<String> {
+    let doc = roxmltree::Document::parse(svg_data)?;
+    if let Some(missing) = ids.iter().find(|&&id| !doc.descendants().any(|node| node.attribute("id") == Some(id))) {
+        return Err(ConvertError::ElementNotFound(format!("找不到id为 {} 的元素", missing)));
+    }
+    
+    let root_end = doc.root_element().range().end;
+    let insert_at = svg_data[..root_end].rfind("</")
+        .ok_or_else(|| ConvertError::ParseSvg("SVG根元素没有可引用的内容".to_string()))?;
+    let mut uses = String::new();
+    for (index, id) in ids.iter().enumerate() {
+        let href = id.replace('&', "&amp;").replace('"', "&quot;").replace('<', "&lt;");
+        uses.push_str(&format!("<use id=\"{}\" href=\"#{}\"/>", target_id(index), href));
+    }
+    Ok(format!("{}{}{}", &svg_data[..insert_at], uses, &svg_data[insert_at..]))
+}
+
+fn target_id(index: usize) -> String {
+    format!("{}-{}", TARGET_ID_PREFIX, index)
+}
+
+/// 列出精灵图中的图标id：所有带id的 `<symbol>` 以及根元素下带id的 `<g>`，按文档顺序排列
+pub fn sprite_ids(svg_data: &str) -> Result<Vec<String>> {
+    let doc = roxmltree::Document::parse(svg_data)?;
+    let root = doc.root_element();
+    let ids = doc.descendants()
+        .filter(|node| match node.tag_name().name() {
+            "symbol" => true,
+            "g" => node.parent() == Some(root),
+            _ => false,
+        })
+        .filter_map(|node| node.attribute("id"))
+        .map(str::to_string)
+        .collect();
+    Ok(ids)
+}
+
+/// 一次解析精灵图，将每个图标按各个缩放倍数分别裁剪渲染并编码
+///
+/// 结果按 `sprite_ids` 的顺序排列，同一图标的不同倍数相邻；没有可渲染内容的图标被跳过。
+/// `padding` 为每个图标包围盒四周的留白（SVG用户单位），`options.crop` 被忽略。
+pub fn render_sprites(svg_data: &str, padding: f32, scales: &[f32], options: &RenderOptions) -> Result<Vec<RenderedImage>> {
+    render_sprites_with_missing_fonts(svg_data, padding, scales, options).map(|(images, _, _)| images)
+}
+
+/// 与 `render_sprites` 相同，同时返回解析时找不到的字体族与被跳过的图标id
+fn render_sprites_with_missing_fonts(
+    svg_data: &str,
+    padding: f32,
+    scales: &[f32],
+    options: &RenderOptions,
+) -> Result<(Vec<RenderedImage>, Vec<String>, Vec<String>)> {
+    if let Some(limits) = &options.limits {
+        limits.check_svg(svg_data)?;
+    }
+    let ids = sprite_ids(svg_data)?;
+    if ids.is_empty() {
+        return Err(ConvertError::ElementNotFound("SVG中没有带id的 <symbol> 或顶层 <g> 元素".to_string()));
+    }
+    
+    // 为不在渲染树中的元素统一注入 `<use>`，只需解析一次
+    let resources = options.effective_resources();
+    let (tree, missing) = parse_svg_with_missing_fonts(svg_data, &options.fonts, &resources)?;
+    let hidden: Vec<&str> = ids.iter()
+        .map(String::as_str)
+        .filter(|id| tree.node_by_id(id).is_none())
+        .collect();
+    let tree = if hidden.is_empty() { tree } else { parse_svg(&inject_uses(svg_data, &hidden)?, &options.fonts, &resources)? };
+    
+    let mut images = Vec::with_capacity(ids.len() * scales.len());
+    let mut skipped = Vec::new();
+    for id in &ids {
+        let node = match hidden.iter().position(|h| h == id) {
+            Some(index) => tree.node_by_id(&target_id(index)),
+            None => tree.node_by_id(id),
+        };
+        // 空分组与 `display:none` 的元素不在渲染树中，或没有包围盒
+        let sprite = match node {
+            Some(node) => isolate_content(&tree, &node, padding)?,
+            None => None,
+        };
+        match sprite {
+            Some(sprite) => images.extend(render_scales(&sprite, Some(id), scales, options)?),
+            None => skipped.push(id.clone()),
+        }
+    }
+    if images.is_empty() {
+        return Err(ConvertError::ElementNotFound("精灵图中的图标都没有可渲染的内容".to_string()));
+    }
+    Ok((images, missing, skipped))
+}
+
+/// 按渲染选项将已解析的SVG渲染为像素图像
+///
+/// `options.crop` 不在此处理，需要时先调用 `crop_tree`。
+pub fn render_tree(tree: &usvg::Tree, options: &RenderOptions) -> Result<tiny_skia::Pixmap> {
+    let Layout { width, height, transform, .. } = layout(tree.size, options)?;
+    if let Some(limits) = &options.limits {
+        limits.check_pixels(width, height)?;
+    }
     
     // 创建像素缓冲区
-    let mut pixmap = tiny_skia::Pixmap::new(width_final, height_final)
-        .ok_or(anyhow!("无法创建像素图像"))?;
-    
-    // 渲染SVG到像素缓冲区
-    let render_tree = resvg::Tree::from_usvg(&tree);
-    let transform = tiny_skia::Transform::from_scale(
-        width_final as f32 / orig_size.width(),
-        height_final as f32 / orig_size.height(),
-    );
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)
+        .ok_or(ConvertError::InvalidDimensions("无法创建像素图像".to_string()))?;
+    fill_background(&mut pixmap, options.background);
+    
+    // 渲染SVG到像素缓冲区（viewBox 到 SVG 尺寸的映射由渲染器处理）
+    let render_tree = resvg::Tree::from_usvg(tree);
     render_tree.render(transform, &mut pixmap.as_mut());
     
-    // 将像素缓冲区转换为PNG数据
-    let png_data = pixmap.encode_png()
-        .map_err(|e| anyhow!("PNG编码错误: {}", e))?;
+    if is_opaque(options) {
+        flatten_alpha(&mut pixmap);
+    }
+    
+    Ok(pixmap)
+}
+
+/// 用背景填充像素缓冲区
+fn fill_background(pixmap: &mut tiny_skia::Pixmap, background: Background) {
+    match background {
+        Background::Transparent => {}
+        Background::Color([r, g, b, a]) => pixmap.fill(tiny_skia::Color::from_rgba8(r, g, b, a)),
+        Background::Checkerboard => {
+            let width = pixmap.width();
+            for (index, pixel) in pixmap.pixels_mut().iter_mut().enumerate() {
+                let (x, y) = (index as u32 % width, index as u32 / width);
+                let [r, g, b, a] = CHECKER_COLORS[((x / CHECKER_SIZE + y / CHECKER_SIZE) % 2) as usize];
+                *pixel = tiny_skia::ColorU8::from_rgba(r, g, b, a).premultiply();
+            }
+        }
+    }
+}
+
+/// 将预乘透明度的像素合成到白色上，结果完全不透明
+fn flatten_alpha(pixmap: &mut tiny_skia::Pixmap) {
+    for pixel in pixmap.pixels_mut() {
+        let white = 255 - pixel.alpha();
+        if let Some(opaque) = tiny_skia::PremultipliedColorU8::from_rgba(
+            pixel.red() + white,
+            pixel.green() + white,
+            pixel.blue() + white,
+            255,
+        ) {
+            *pixel = opaque;
+        }
+    }
+}
+
+/// 输出是否不带透明通道：指定了 `flatten` 或格式不支持透明通道
+fn is_opaque(options: &RenderOptions) -> bool {
+    options.flatten || !options.format.supports_alpha()
+}
+
+/// 按 `options.format` 编码由 `render_tree` 渲染的像素图像
+///
+/// `dpi` 为输出像素对应的（水平, 垂直）分辨率，由 `output_dpi` 计算，仅在PNG写入pHYs块时使用。
+pub fn encode_image(pixmap: &tiny_skia::Pixmap, dpi: (f32, f32), options: &RenderOptions) -> Result<Vec<u8>> {
+    let mut encoded = Vec::new();
+    let result = match options.format {
+        OutputFormat::Png => return encode_png(pixmap, dpi, options),
+        OutputFormat::Jpeg => {
+            if !(1..=100).contains(&options.quality) {
+                return Err(ConvertError::InvalidOption(format!("JPEG质量必须在1到100之间: {}", options.quality)));
+            }
+            write_image(image::codecs::jpeg::JpegEncoder::new_with_quality(&mut encoded, options.quality), pixmap, options)
+        }
+        OutputFormat::WebP => write_image(image::codecs::webp::WebPEncoder::new_lossless(&mut encoded), pixmap, options),
+        OutputFormat::Tiff => write_image(image::codecs::tiff::TiffEncoder::new(std::io::Cursor::new(&mut encoded)), pixmap, options),
+        OutputFormat::Bmp => write_image(image::codecs::bmp::BmpEncoder::new(&mut encoded), pixmap, options),
+        OutputFormat::Qoi => write_image(image::codecs::qoi::QoiEncoder::new(&mut encoded), pixmap, options),
+    };
+    result.map_err(|e| ConvertError::Encode(format!("{:?}编码错误: {}", options.format, e)))?;
+    Ok(encoded)
+}
+
+/// 用 `image` 库的编码器写出像素，格式不支持透明通道或指定了 `flatten` 时写为RGB
+fn write_image(encoder: impl ImageEncoder, pixmap: &tiny_skia::Pixmap, options: &RenderOptions) -> image::ImageResult<()> {
+    let pixels = demultiply(pixmap);
+    let (data, color_type): (Vec<u8>, _) = if is_opaque(options) {
+        (pixels.iter().flat_map(|p| [p[0], p[1], p[2]]).collect(), image::ColorType::Rgb8)
+    } else {
+        (pixels.concat(), image::ColorType::Rgba8)
+    };
+    encoder.write_image(&data, pixmap.width(), pixmap.height(), color_type)
+}
+
+/// 将预乘透明度的像素转换为普通RGBA
+fn demultiply(pixmap: &tiny_skia::Pixmap) -> Vec<[u8; 4]> {
+    pixmap.pixels()
+        .iter()
+        .map(|p| {
+            let c = p.demultiply();
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        })
+        .collect()
+}
+
+/// 按 `options.png` 编码为PNG；`flatten` 为真时不带透明通道
+pub fn encode_png(pixmap: &tiny_skia::Pixmap, dpi: (f32, f32), options: &RenderOptions) -> Result<Vec<u8>> {
+    let png_options = &options.png;
+    let opaque = is_opaque(options);
+    let (width, height) = (pixmap.width(), pixmap.height());
+    let sixteen_bit = match png_options.bit_depth {
+        8 => false,
+        16 if png_options.color != PngColor::Palette => true,
+        16 => return Err(ConvertError::InvalidOption("索引色PNG只支持8位".to_string())),
+        depth => return Err(ConvertError::InvalidOption(format!("PNG位深必须为8或16: {}", depth))),
+    };
+    if !(1..=256).contains(&png_options.palette_colors) {
+        return Err(ConvertError::InvalidOption(format!("调色板颜色数必须在1到256之间: {}", png_options.palette_colors)));
+    }
+    
+    let pixels = demultiply(pixmap);
+    let mut palette = None;
+    let (color_type, mut data): (_, Vec<u8>) = match png_options.color {
+        PngColor::Truecolor if opaque => {
+            (png::ColorType::Rgb, pixels.iter().flat_map(|p| [p[0], p[1], p[2]]).collect())
+        }
+        PngColor::Truecolor => (png::ColorType::Rgba, pixels.concat()),
+        PngColor::Grayscale => {
+            // Rec. 709 亮度系数
+            let luma = |p: &[u8; 4]| {
+                ((2126 * p[0] as u32 + 7152 * p[1] as u32 + 722 * p[2] as u32 + 5000) / 10000) as u8
+            };
+            if opaque {
+                (png::ColorType::Grayscale, pixels.iter().map(luma).collect())
+            } else {
+                (png::ColorType::GrayscaleAlpha, pixels.iter().flat_map(|p| [luma(p), p[3]]).collect())
+            }
+        }
+        PngColor::Palette => {
+            let (colors, indices) = super::palette::build_palette(&pixels, png_options.palette_colors as usize);
+            palette = Some(colors);
+            (png::ColorType::Indexed, indices)
+        }
+    };
     
+    let mut png_data = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png_data, width, height);
+    encoder.set_color(color_type);
+    encoder.set_compression(match png_options.compression {
+        PngCompression::Fast => png::Compression::Fast,
+        PngCompression::Default => png::Compression::Default,
+        PngCompression::Best => png::Compression::Best,
+    });
+    
+    if let Some(colors) = &palette {
+        // 颜色少时使用更低的位深
+        let (depth, bits) = match colors.len() {
+            0..=2 => (png::BitDepth::One, 1),
+            3..=4 => (png::BitDepth::Two, 2),
+            5..=16 => (png::BitDepth::Four, 4),
+            _ => (png::BitDepth::Eight, 8),
+        };
+        encoder.set_depth(depth);
+        data = pack_indices(&data, width as usize, bits);
+        encoder.set_palette(colors.iter().flat_map(|c| [c[0], c[1], c[2]]).collect::<Vec<u8>>());
+        // 半透明颜色排在调色板前面，tRNS只需覆盖到最后一个不透明度小于255的颜色
+        let trns: Vec<u8> = colors.iter().map(|c| c[3]).take_while(|&a| a < 255).collect();
+        if !trns.is_empty() {
+            encoder.set_trns(trns);
+        }
+    } else if sixteen_bit {
+        encoder.set_depth(png::BitDepth::Sixteen);
+        // 8位样本扩展为16位大端序（v * 257）
+        data = data.iter().flat_map(|&v| [v, v]).collect();
+    } else {
+        encoder.set_depth(png::BitDepth::Eight);
+    }
+    
+    if png_options.write_dpi {
+        let per_meter = |dpi: f32| (dpi / 0.0254).round() as u32;
+        encoder.set_pixel_dims(Some(png::PixelDimensions {
+            xppu: per_meter(dpi.0),
+            yppu: per_meter(dpi.1),
+            unit: png::Unit::Meter,
+        }));
+    }
+    for (keyword, text) in &png_options.text {
+        let result = if text.chars().all(|c| (c as u32) < 256) {
+            encoder.add_text_chunk(keyword.clone(), text.clone())
+        } else {
+            encoder.add_itxt_chunk(keyword.clone(), text.clone())
+        };
+        result.map_err(|e| ConvertError::InvalidOption(format!("PNG文本元数据错误: {}", e)))?;
+    }
+    
+    let mut writer = encoder.write_header().map_err(|e| ConvertError::Encode(format!("PNG编码错误: {}", e)))?;
+    writer.write_image_data(&data).map_err(|e| ConvertError::Encode(format!("PNG编码错误: {}", e)))?;
+    writer.finish().map_err(|e| ConvertError::Encode(format!("PNG编码错误: {}", e)))?;
     Ok(png_data)
 }
 
-/// 从SVG文件转换为PNG文件
-pub fn convert_svg_file_to_png_file(
-    input_path: &str,
-    output_path: &str,
+/// 将调色板下标按位深打包，每行按字节对齐
+fn pack_indices(indices: &[u8], width: usize, depth: usize) -> Vec<u8> {
+    if depth == 8 {
+        return indices.to_vec();
+    }
+    let per_byte = 8 / depth;
+    let mut packed = Vec::with_capacity(indices.len() / per_byte + indices.len() / width.max(1) + 1);
+    for row in indices.chunks(width.max(1)) {
+        for chunk in row.chunks(per_byte) {
+            let mut byte = 0u8;
+            for (i, &index) in chunk.iter().enumerate() {
+                byte |= index << (8 - depth * (i + 1));
+            }
+            packed.push(byte);
+        }
+    }
+    packed
+}
+
+/// 输出图像的尺寸与从SVG尺寸到输出像素的变换
+struct Layout {
     width: u32,
     height: u32,
-) -> Result<()> {
-    // 创建临时文件路径用于存储处理后的SVG
-    let temp_svg = format!("{}_temp_text2path.svg", input_path.split('.').next().unwrap_or_default());
+    transform: tiny_skia::Transform,
+    /// 每个SVG用户单位对应的（水平, 垂直）像素数，不含取整的影响
+    scale: (f32, f32),
+}
+
+/// 计算输出尺寸以及从SVG尺寸到输出像素的变换
+fn layout(size: usvg::Size, options: &RenderOptions) -> Result<Layout> {
+    let (svg_width, svg_height) = (size.width(), size.height());
+    let scaled = |length: f32, scale: f32| (length * scale).round().max(1.0) as u32;
     
-    // 尝试将文本转换为路径
-    let processed_svg = convert_text_to_path(input_path, &temp_svg)?;
+    let (width, height, scale) = match options.output_size() {
+        (Some(width), Some(height)) => {
+            let (sx, sy) = (width as f32 / svg_width, height as f32 / svg_height);
+            let scale = match options.fit {
+                FitMode::Stretch => {
+                    let transform = tiny_skia::Transform::from_scale(sx, sy);
+                    return Ok(Layout { width, height, transform, scale: (sx, sy) });
+                }
+                FitMode::Contain => sx.min(sy),
+                FitMode::Cover => sx.max(sy),
+            };
+            // 居中放置，Contain 留出边距，Cover 裁掉超出部分
+            let dx = (width as f32 - svg_width * scale) / 2.0;
+            let dy = (height as f32 - svg_height * scale) / 2.0;
+            let transform = tiny_skia::Transform::from_row(scale, 0.0, 0.0, scale, dx, dy);
+            return Ok(Layout { width, height, transform, scale: (scale, scale) });
+        }
+        (Some(width), None) => {
+            let scale = width as f32 / svg_width;
+            (width, scaled(svg_height, scale), scale)
+        }
+        (None, Some(height)) => {
+            let scale = height as f32 / svg_height;
+            (scaled(svg_width, scale), height, scale)
+        }
+        (None, None) => {
+            if options.zoom <= 0.0 || options.dpi <= 0.0 {
+                return Err(ConvertError::InvalidDimensions("缩放倍数和DPI必须大于0".to_string()));
+            }
+            let scale = options.zoom * options.dpi / CSS_DPI;
+            (scaled(svg_width, scale), scaled(svg_height, scale), scale)
+        }
+    };
     
-    // 读取处理后的SVG
-    let svg_data = fs::read_to_string(&processed_svg)?;
+    // 按取整后的尺寸分别计算两个方向的缩放，使图像恰好铺满输出
+    let transform = tiny_skia::Transform::from_scale(width as f32 / svg_width, height as f32 / svg_height);
+    Ok(Layout { width, height, transform, scale: (scale, scale) })
+}
+
+/// 输出像素对应的（水平, 垂直）分辨率，按SVG中 1px = 1/96 英寸换算
+///
+/// 未指定宽高时为 `zoom × dpi`；指定了宽高或适配方式时由实际的缩放比例得出，
+/// 以便按该分辨率打印时保持SVG的物理尺寸。
+pub fn output_dpi(size: usvg::Size, options: &RenderOptions) -> Result<(f32, f32)> {
+    let (sx, sy) = layout(size, options)?.scale;
+    Ok((sx * CSS_DPI, sy * CSS_DPI))
+}
+
+/// 读取SVG文件，错误信息中包含文件路径
+///
+/// 设置了 `limits` 时先按文件大小检查 `max_input_bytes`，且最多读取上限多一个字节。
+pub fn read_svg_file(path: &Path, limits: Option<&Limits>) -> Result<String> {
+    let context = || format!("无法读取 {}", path.display());
+    let file = fs::File::open(path).map_err(|e| ConvertError::io(context(), e))?;
+    if let Some(limits) = limits {
+        let size = file.metadata().map_err(|e| ConvertError::io(context(), e))?.len();
+        if size > limits.max_input_bytes as u64 {
+            return Err(LimitError::InputTooLarge { size: size as usize, max: limits.max_input_bytes }.into());
+        }
+    }
+    read_svg(file, limits, context)
+}
+
+/// 读入SVG数据；设置了 `limits` 时最多读取 `max_input_bytes + 1` 字节，超出上限即停止读取
+fn read_svg(reader: impl Read, limits: Option<&Limits>, context: impl Fn() -> String) -> Result<String> {
+    let mut data = Vec::new();
+    let max = limits.map(|limits| limits.max_input_bytes);
+    reader.take(max.map_or(u64::MAX, |max| max as u64 + 1))
+        .read_to_end(&mut data)
+        .map_err(|e| ConvertError::io(context(), e))?;
+    if let Some(max) = max.filter(|&max| data.len() > max) {
+        return Err(LimitError::InputTooLarge { size: data.len(), max }.into());
+    }
+    String::from_utf8(data)
+        .map_err(|e| ConvertError::io(context(), std::io::Error::new(std::io::ErrorKind::InvalidData, e)))
+}
+
+/// 写入输出文件，错误信息中包含文件路径
+fn write_output_file(path: &Path, data: &[u8]) -> Result<()> {
+    fs::write(path, data).map_err(|e| ConvertError::io(format!("无法写入 {}", path.display()), e))
+}
+
+/// 未指定资源目录时，相对路径的图像按输入文件所在目录解析
+fn with_input_dir(options: &RenderOptions, input_path: &Path) -> RenderOptions {
+    RenderOptions {
+        resources: options.resources.with_input_dir(input_path),
+        ..options.clone()
+    }
+}
+
+/// 从SVG文件转换为图像文件，格式由 `options.format` 指定
+///
+/// 整个过程在内存中完成，除输出文件外不会创建其他文件；转换失败时不会写入输出文件。
+/// 未设置 `options.resources.resources_dir` 时，引用的图像相对于输入文件所在目录查找。
+pub fn convert_svg_file_to_png_file(
+    input_path: impl AsRef<Path>,
+    output_path: impl AsRef<Path>,
+    options: &RenderOptions,
+) -> Result<FileReport> {
+    // 读取SVG文件，文本在解析时按 `options.fonts` 转换为路径
+    let (input_path, output_path) = (input_path.as_ref(), output_path.as_ref());
+    let svg_data = read_svg_file(input_path, options.limits.as_ref())?;
     
     // 使用核心函数进行转换
-    let png_data = convert_svg_to_png(&svg_data, width, height)?;
-    
-    // 保存PNG文件
-    fs::write(output_path, &png_data)?;
-    
-    // 清理临时文件
-    if processed_svg != input_path {
-        let _ = fs::remove_file(&processed_svg);
-    }
+    let options = with_input_dir(options, input_path);
+    let (tree, missing_fonts) = parse_input_with_missing_fonts(&svg_data, &options)?;
+    let png_data = render_and_encode(&tree, &options)?;
     
+    // 保存图像文件
+    write_output_file(output_path, &png_data)?;
+    Ok(FileReport { written: vec![output_path.to_path_buf()], missing_fonts, ..Default::default() })
+}
+
+/// 从读取器读入SVG，将渲染结果写入写入器，格式由 `options.format` 指定
+///
+/// 设置了 `options.limits` 时最多读取 `max_input_bytes + 1` 字节，超出上限时返回错误而不读完整个输入。
+pub fn convert_svg_reader_to_png_writer(
+    reader: impl Read,
+    mut writer: impl Write,
+    options: &RenderOptions,
+) -> Result<()> {
+    let svg_data = read_svg(reader, options.limits.as_ref(), || "无法读取SVG数据".to_string())?;
+    let png_data = convert_svg_to_png(&svg_data, options)?;
+    writer.write_all(&png_data)?;
+    writer.flush()?;
     Ok(())
 }
 
-/// 将SVG中的文本转换为路径（针对文件操作）
-pub fn convert_text_to_path(input: &str, output: &str) -> Result<String> {
-    // 读取SVG文件
-    let svg_content = fs::read_to_string(input)?;
+/// 将SVG文件按多个缩放倍数渲染为图像文件，返回写入的文件路径与找不到的字体族
+///
+/// 输出路径由 `template` 生成，`{scale}` 替换为缩放倍数（见 `RenderedImage::scale`）；
+/// 模板中没有 `{scale}` 时在扩展名前插入 `@{scale}x`，如 `icon.png` → `icon@2x.png`。
+pub fn convert_svg_file_to_png_files(
+    input_path: impl AsRef<Path>,
+    template: &str,
+    scales: &[f32],
+    options: &RenderOptions,
+) -> Result<FileReport> {
+    let input_path = input_path.as_ref();
+    let svg_data = read_svg_file(input_path, options.limits.as_ref())?;
+    let options = with_input_dir(options, input_path);
+    let (tree, missing_fonts) = parse_input_with_missing_fonts(&svg_data, &options)?;
+    let images = render_scales(&tree, None, scales, &options)?;
     
-    // 解析XML
-    let doc = roxmltree::Document::parse(&svg_content)?;
+    let template = if template.contains("{scale}") {
+        template.to_string()
+    } else {
+        match template.rfind('.').filter(|&dot| !template[dot..].contains(['/', '\\'])) {
+            Some(dot) => format!("{}@{{scale}}x{}", &template[..dot], &template[dot..]),
+            None => format!("{}@{{scale}}x", template),
+        }
+    };
+    let written = write_images(&images, Path::new(""), &template)?;
+    Ok(FileReport { written, missing_fonts, ..Default::default() })
+}
+
+/// 将精灵图文件中的每个图标按各个缩放倍数导出为图像文件，返回写入的文件路径、找不到的字体族与跳过的图标
+///
+/// 文件名由 `template` 生成：`{id}` 替换为图标id（路径分隔符替换为 `_`），
+/// `{scale}` 替换为缩放倍数（见 `RenderedImage::scale`）。输出目录不存在时自动创建。
+pub fn export_sprite_file(
+    input_path: impl AsRef<Path>,
+    output_dir: impl AsRef<Path>,
+    template: &str,
+    padding: f32,
+    scales: &[f32],
+    options: &RenderOptions,
+) -> Result<FileReport> {
+    let input_path = input_path.as_ref();
+    let svg_data = read_svg_file(input_path, options.limits.as_ref())?;
+    let (sprites, missing_fonts, skipped) =
+        render_sprites_with_missing_fonts(&svg_data, padding, scales, &with_input_dir(options, input_path))?;
+    
+    let output_dir = output_dir.as_ref();
+    fs::create_dir_all(output_dir)
+        .map_err(|e| ConvertError::io(format!("无法创建目录 {}", output_dir.display()), e))?;
+    let written = write_images(&sprites, output_dir, template)?;
+    Ok(FileReport { written, missing_fonts, skipped })
+}
+
+/// 按文件名模板写出批量渲染的结果
+fn write_images(images: &[RenderedImage], dir: &Path, template: &str) -> Result<Vec<PathBuf>> {
+    let mut written = Vec::with_capacity(images.len());
+    for image in images {
+        let id = image.id.as_deref().unwrap_or_default().replace(['/', '\\'], "_");
+        let name = template
+            .replace("{id}", &id)
+            .replace("{scale}", &format_scale(image.scale));
+        let path = dir.join(name);
+        write_output_file(&path, &image.data)?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// 去掉缩放倍数中多余的小数位，如 `2.0` → `2`、`1.50` → `1.5`
+fn format_scale(scale: f32) -> String {
+    let text = format!("{:.2}", scale);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// 将SVG中的文本按 `fonts` 中的字体转换为路径，返回不再依赖字体的SVG
+///
+/// 含文本的SVG会按usvg的简化结构重新输出（样式展开为属性、`<use>` 展开等，
+/// 按 `resources` 找到的图像以data URL内嵌），不含 `<text>` 的SVG原样返回。
+/// 设置了 `limits` 时与渲染的安全模式相同：先检查资源上限，且不读取外部文件。
+pub fn convert_text_to_path(
+    svg_data: &str,
+    fonts: &FontOptions,
+    resources: &ResourceOptions,
+    limits: Option<&Limits>,
+) -> Result<String> {
+    outline_text(svg_data, fonts, resources, limits).map(|(svg, _)| svg)
+}
+
+/// 与 `convert_text_to_path` 相同，同时返回找不到的字体族
+fn outline_text(
+    svg_data: &str,
+    fonts: &FontOptions,
+    resources: &ResourceOptions,
+    limits: Option<&Limits>,
+) -> Result<(String, Vec<String>)> {
+    if let Some(limits) = limits {
+        limits.check_svg(svg_data)?;
+    }
     
     // 检查是否包含text元素
+    let doc = roxmltree::Document::parse(svg_data)?;
     let has_text = doc.descendants().any(|node| node.tag_name().name() == "text");
-    
-    if has_text {
-        // 使用更安全的SVG处理方式
-        // 不修改原始SVG，而是让usvg库自己去处理文本
-        fs::copy(input, output)?;
-        println!("SVG文件包含文本元素，将使用内置SVG渲染器处理文本。");
-    } else {
-        // 如果没有文本元素，直接复制
-        fs::copy(input, output)?;
+    if !has_text {
+        return Ok((svg_data.to_string(), Vec::new()));
     }
     
-    Ok(output.to_string())
-} 
\ No newline at end of file
+    let (tree, missing) = parse_svg_with_missing_fonts(svg_data, fonts, &secure_resources(resources, limits))?;
+    Ok((tree.to_string(&usvg::XmlOptions::default()), missing))
+}
+
+/// 将SVG文件中的文本转换为路径并写入输出文件，返回写入的文件路径与找不到的字体族
+pub fn convert_text_to_path_file(
+    input_path: impl AsRef<Path>,
+    output_path: impl AsRef<Path>,
+    fonts: &FontOptions,
+    resources: &ResourceOptions,
+    limits: Option<&Limits>,
+) -> Result<FileReport> {
+    let (input_path, output_path) = (input_path.as_ref(), output_path.as_ref());
+    let svg_data = read_svg_file(input_path, limits)?;
+    let (outlined, missing_fonts) = outline_text(&svg_data, fonts, &resources.with_input_dir(input_path), limits)?;
+    write_output_file(output_path, outlined.as_bytes())?;
+    Ok(FileReport { written: vec![output_path.to_path_buf()], missing_fonts, ..Default::default() })
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn svg_size() -> usvg::Size {
+        usvg::Size::from_wh(200.0, 100.0).unwrap()
+    }
+
+    fn sized(width: Option<u32>, height: Option<u32>, fit: FitMode) -> RenderOptions {
+        RenderOptions { width, height, fit, ..Default::default() }
+    }
+
+    /// (宽, 高, 水平缩放, 垂直缩放, 水平偏移, 垂直偏移)
+    fn layout_of(options: &RenderOptions) -> (u32, u32, f32, f32, f32, f32) {
+        let Layout { width, height, transform, .. } = layout(svg_size(), options).unwrap();
+        (width, height, transform.sx, transform.sy, transform.tx, transform.ty)
+    }
+
+    #[test]
+    fn layout_fit_modes() {
+        let square = |fit| layout_of(&sized(Some(100), Some(100), fit));
+        assert_eq!(square(FitMode::Stretch), (100, 100, 0.5, 1.0, 0.0, 0.0));
+        // 等比缩放到100×50，上下各留25像素
+        assert_eq!(square(FitMode::Contain), (100, 100, 0.5, 0.5, 0.0, 25.0));
+        // 等比缩放到200×100，左右各裁掉50像素
+        assert_eq!(square(FitMode::Cover), (100, 100, 1.0, 1.0, -50.0, 0.0));
+    }
+
+    #[test]
+    fn layout_with_one_side_keeps_aspect_ratio() {
+        assert_eq!(layout_of(&sized(Some(100), None, FitMode::Contain)), (100, 50, 0.5, 0.5, 0.0, 0.0));
+        assert_eq!(layout_of(&sized(None, Some(50), FitMode::Cover)), (100, 50, 0.5, 0.5, 0.0, 0.0));
+    }
+
+    #[test]
+    fn layout_treats_zero_as_unset() {
+        let native = layout_of(&RenderOptions::default());
+        assert_eq!(native, (200, 100, 1.0, 1.0, 0.0, 0.0));
+        assert_eq!(layout_of(&sized(Some(0), Some(0), FitMode::Stretch)), native);
+        assert_eq!(layout_of(&sized(Some(0), Some(50), FitMode::Stretch)), (100, 50, 0.5, 0.5, 0.0, 0.0));
+        assert_eq!(layout_of(&sized(Some(100), Some(0), FitMode::Stretch)), (100, 50, 0.5, 0.5, 0.0, 0.0));
+    }
+
+    #[test]
+    fn layout_zoom_and_dpi() {
+        let zoomed = RenderOptions { zoom: 2.0, ..Default::default() };
+        assert_eq!(layout_of(&zoomed), (400, 200, 2.0, 2.0, 0.0, 0.0));
+        let dpi = RenderOptions { dpi: 144.0, ..Default::default() };
+        assert_eq!(layout_of(&dpi), (300, 150, 1.5, 1.5, 0.0, 0.0));
+        // 指定了宽高时忽略缩放倍数
+        let both = RenderOptions { zoom: 3.0, ..sized(Some(100), None, FitMode::Contain) };
+        assert_eq!(layout_of(&both).0, 100);
+
+        let zero = RenderOptions { zoom: 0.0, ..Default::default() };
+        assert!(matches!(layout(svg_size(), &zero), Err(ConvertError::InvalidDimensions(_))));
+    }
+
+    /// 8×4：左半红色、右半蓝色，右下角透明
+    const TWO_COLORS: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" width="8" height="4">
+        <rect width="4" height="4" fill="red"/><rect x="4" width="4" height="2" fill="blue"/>
+    </svg>"#;
+
+    fn png_info(png: &[u8]) -> png::Info<'static> {
+        png::Decoder::new(png).read_info().unwrap().info().clone()
+    }
+
+    fn rgba(png: &[u8]) -> image::RgbaImage {
+        image::load_from_memory(png).unwrap().to_rgba8()
+    }
+
+    fn with_png(png: PngOptions) -> RenderOptions {
+        RenderOptions { png, ..Default::default() }
+    }
+
+    #[test]
+    fn palette_png_matches_truecolor() {
+        let truecolor = convert_svg_to_png(TWO_COLORS, &RenderOptions::default()).unwrap();
+        let indexed = convert_svg_to_png(TWO_COLORS, &with_png(PngOptions { color: PngColor::Palette, ..Default::default() })).unwrap();
+        let info = png_info(&indexed);
+        assert_eq!(info.color_type, png::ColorType::Indexed);
+        // 红、蓝、透明三种颜色使用2位索引，透明色记录在tRNS中
+        assert_eq!(info.bit_depth, png::BitDepth::Two);
+        assert_eq!(info.palette.as_ref().unwrap().len(), 3 * 3);
+        assert_eq!(info.trns.as_deref(), Some(&[0u8][..]));
+        assert_eq!(rgba(&indexed), rgba(&truecolor));
+
+        let flat = RenderOptions { flatten: true, ..with_png(PngOptions { color: PngColor::Palette, ..Default::default() }) };
+        let info = png_info(&convert_svg_to_png(TWO_COLORS, &flat).unwrap());
+        assert_eq!(info.bit_depth, png::BitDepth::Two);
+        assert!(info.trns.is_none());
+    }
+
+    #[test]
+    fn grayscale_png_uses_luma() {
+        let gray = with_png(PngOptions { color: PngColor::Grayscale, ..Default::default() });
+        let png = convert_svg_to_png(TWO_COLORS, &gray).unwrap();
+        assert_eq!(png_info(&png).color_type, png::ColorType::GrayscaleAlpha);
+        let image = image::load_from_memory(&png).unwrap().to_luma_alpha8();
+        // Rec. 709：红色亮度0.2126，蓝色0.0722
+        assert_eq!(image.get_pixel(0, 0).0, [54, 255]);
+        assert_eq!(image.get_pixel(7, 0).0, [18, 255]);
+        assert_eq!(image.get_pixel(7, 3).0[1], 0);
+
+        let flat = RenderOptions { flatten: true, ..gray };
+        assert_eq!(png_info(&convert_svg_to_png(TWO_COLORS, &flat).unwrap()).color_type, png::ColorType::Grayscale);
+    }
+
+    #[test]
+    fn sixteen_bit_png_widens_samples() {
+        let deep = with_png(PngOptions { bit_depth: 16, ..Default::default() });
+        let png = convert_svg_to_png(TWO_COLORS, &deep).unwrap();
+        let info = png_info(&png);
+        assert_eq!((info.color_type, info.bit_depth), (png::ColorType::Rgba, png::BitDepth::Sixteen));
+        let wide = image::load_from_memory(&png).unwrap().to_rgba16();
+        assert_eq!(wide.get_pixel(0, 0).0, [65535, 0, 0, 65535]);
+        assert_eq!(rgba(&png), rgba(&convert_svg_to_png(TWO_COLORS, &RenderOptions::default()).unwrap()));
+
+        let indexed = with_png(PngOptions { color: PngColor::Palette, bit_depth: 16, ..Default::default() });
+        assert!(matches!(convert_svg_to_png(TWO_COLORS, &indexed), Err(ConvertError::InvalidOption(_))));
+    }
+
+    #[test]
+    fn phys_follows_the_render_scale() {
+        let dims = |options: RenderOptions| {
+            let options = RenderOptions { png: PngOptions { write_dpi: true, ..Default::default() }, ..options };
+            let dims = png_info(&convert_svg_to_png(TWO_COLORS, &options).unwrap()).pixel_dims.unwrap();
+            assert_eq!(dims.unit, png::Unit::Meter);
+            (dims.xppu, dims.yppu)
+        };
+        // 96 DPI ≈ 3780 像素/米
+        assert_eq!(dims(RenderOptions::default()), (3780, 3780));
+        assert_eq!(dims(RenderOptions { zoom: 2.0, ..Default::default() }), (7559, 7559));
+        assert_eq!(dims(RenderOptions { dpi: 300.0, ..Default::default() }), (11811, 11811));
+        // 拉伸到16×4时水平192 DPI、垂直96 DPI
+        assert_eq!(dims(sized(Some(16), Some(4), FitMode::Stretch)), (7559, 3780));
+        assert!(png_info(&convert_svg_to_png(TWO_COLORS, &RenderOptions::default()).unwrap()).pixel_dims.is_none());
+    }
+
+    #[test]
+    fn sprites_without_content_are_skipped() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="30" height="10">
+            <g id="square"><rect width="10" height="10" fill="red"/></g>
+            <g id="empty"/>
+            <g id="hidden" style="display:none"><rect x="20" width="10" height="10"/></g>
+        </svg>"#;
+        let (images, _, skipped) = render_sprites_with_missing_fonts(svg, 0.0, &[1.0], &RenderOptions::default()).unwrap();
+        let ids: Vec<_> = images.iter().map(|image| image.id.as_deref()).collect();
+        assert_eq!(ids, [Some("square")]);
+        assert_eq!(skipped, ["empty", "hidden"]);
+
+        let only_empty = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><g id="empty"/></svg>"#;
+        assert!(matches!(render_sprites(only_empty, 0.0, &[1.0], &RenderOptions::default()), Err(ConvertError::ElementNotFound(_))));
+    }
+}
@@ -0,0 +1,316 @@
+use image::GrayImage;
+
+/// 方向：右、下、左、上（图像坐标系，y 轴向下）
+const DIRECTIONS: [(i32, i32); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+/// 二值区域的一条闭合边界
+///
+/// 顶点位于像素角点上。外轮廓为顺时针（屏幕方向），孔洞轮廓为逆时针，
+/// 因此前景始终位于行进方向的右侧。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contour {
+    /// 方向改变处的顶点，首尾隐式相连
+    pub points: Vec<(f32, f32)>,
+    /// 是否为孔洞轮廓
+    pub is_hole: bool,
+    /// 父轮廓下标：孔洞的父轮廓是其所属区域的外轮廓，
+    /// 位于孔洞内部的外轮廓的父轮廓是该孔洞，最外层轮廓为 `None`
+    pub parent: Option<usize>,
+}
+
+/// 沿像素边追踪二值图像中所有区域的闭合边界
+///
+/// 掩码中非零像素视为前景。对角相接的前景像素视为不连通（前景 4 连通，背景 8 连通），
+/// 每条像素边界恰好属于一个轮廓，不会出现重复或开放的折线。
+pub fn trace_contours(mask: &GrayImage) -> Vec<Contour> {
+    let (width, height) = mask.dimensions();
+    let (w, h) = (width as i32, height as i32);
+    let is_fg = |x: i32, y: i32| x >= 0 && y >= 0 && x < w && y < h && mask.get_pixel(x as u32, y as u32)[0] != 0;
+
+    // 每个角点上出射边的方向位掩码
+    let stride = (w + 1) as usize;
+    let vertex = |x: i32, y: i32| y as usize * stride + x as usize;
+    let mut outgoing = vec![0u8; stride * (h + 1) as usize];
+
+    for y in 0..h {
+        for x in 0..w {
+            if !is_fg(x, y) {
+                continue;
+            }
+            // 每条与背景相邻的像素边生成一条有向边，使前景位于右侧
+            if !is_fg(x, y - 1) {
+                outgoing[vertex(x, y)] |= 1 << 0;
+            }
+            if !is_fg(x + 1, y) {
+                outgoing[vertex(x + 1, y)] |= 1 << 1;
+            }
+            if !is_fg(x, y + 1) {
+                outgoing[vertex(x + 1, y + 1)] |= 1 << 2;
+            }
+            if !is_fg(x - 1, y) {
+                outgoing[vertex(x, y + 1)] |= 1 << 3;
+            }
+        }
+    }
+
+    let labels = label_components(mask);
+    let mut remaining = outgoing.clone();
+    let mut contours = Vec::new();
+    // 每条轮廓右侧的一个前景像素
+    let mut samples = Vec::new();
+
+    for y in 0..=h {
+        for x in 0..=w {
+            while remaining[vertex(x, y)] != 0 {
+                let start_dir = remaining[vertex(x, y)].trailing_zeros() as usize;
+                samples.push(match start_dir {
+                    0 => (x, y),
+                    1 => (x - 1, y),
+                    2 => (x - 1, y - 1),
+                    _ => (x, y - 1),
+                });
+                let mut points = Vec::new();
+                let (mut cx, mut cy) = (x, y);
+                let mut dir = start_dir;
+                let mut area2 = 0i64;
+
+                loop {
+                    remaining[vertex(cx, cy)] &= !(1 << dir);
+                    let (nx, ny) = (cx + DIRECTIONS[dir].0, cy + DIRECTIONS[dir].1);
+                    area2 += cx as i64 * ny as i64 - nx as i64 * cy as i64;
+                    cx = nx;
+                    cy = ny;
+
+                    // 鞍点处优先右转，保证边的后继关系只取决于局部结构
+                    let available = outgoing[vertex(cx, cy)];
+                    let next = [(dir + 1) % 4, dir, (dir + 3) % 4]
+                        .into_iter()
+                        .find(|&d| available & (1 << d) != 0)
+                        .unwrap_or(dir);
+
+                    if next != dir {
+                        points.push((cx as f32, cy as f32));
+                    }
+                    if (cx, cy) == (x, y) && next == start_dir {
+                        break;
+                    }
+                    dir = next;
+                }
+
+                contours.push(Contour {
+                    points,
+                    is_hole: area2 < 0,
+                    parent: None,
+                });
+            }
+        }
+    }
+
+    link_hierarchy(&mut contours, &samples, &labels, width);
+    contours
+}
+
+/// 计算轮廓层级
+fn link_hierarchy(contours: &mut [Contour], samples: &[(i32, i32)], labels: &[usize], width: u32) {
+    let component = |i: usize| {
+        let (x, y) = samples[i];
+        labels[y as usize * width as usize + x as usize]
+    };
+
+    // 同一连通区域的外轮廓只有一条
+    let mut outer_of = std::collections::HashMap::new();
+    for (i, contour) in contours.iter().enumerate() {
+        if !contour.is_hole {
+            outer_of.insert(component(i), i);
+        }
+    }
+
+    let holes: Vec<usize> = (0..contours.len()).filter(|&i| contours[i].is_hole).collect();
+    let hole_bounds: Vec<_> = holes.iter().map(|&i| bounds(&contours[i].points)).collect();
+    let hole_areas: Vec<f32> = holes.iter().map(|&i| signed_area(&contours[i].points).abs()).collect();
+
+    for i in 0..contours.len() {
+        if contours[i].is_hole {
+            contours[i].parent = outer_of.get(&component(i)).copied();
+            continue;
+        }
+
+        // 用区域内某个像素的中心点判断它落在哪些孔洞里，取面积最小者
+        let (sx, sy) = samples[i];
+        let point = (sx as f32 + 0.5, sy as f32 + 0.5);
+        contours[i].parent = holes.iter()
+            .enumerate()
+            .filter(|&(k, _)| {
+                let (min_x, min_y, max_x, max_y) = hole_bounds[k];
+                point.0 > min_x && point.0 < max_x && point.1 > min_y && point.1 < max_y
+            })
+            .filter(|&(_, &h)| contains(&contours[h].points, point))
+            .min_by(|a, b| hole_areas[a.0].total_cmp(&hole_areas[b.0]))
+            .map(|(_, &h)| h);
+    }
+}
+
+/// 以4连通方式标记前景连通区域
+fn label_components(mask: &GrayImage) -> Vec<usize> {
+    let (width, height) = mask.dimensions();
+    let (w, h) = (width as usize, height as usize);
+    let mut labels = vec![usize::MAX; w * h];
+    let mut next = 0;
+    let mut stack = Vec::new();
+
+    for start in 0..w * h {
+        if labels[start] != usize::MAX || mask.as_raw()[start] == 0 {
+            continue;
+        }
+        labels[start] = next;
+        stack.push(start);
+        while let Some(index) = stack.pop() {
+            let (x, y) = (index % w, index / w);
+            let neighbors = [
+                (x > 0).then(|| index - 1),
+                (x + 1 < w).then(|| index + 1),
+                (y > 0).then(|| index - w),
+                (y + 1 < h).then(|| index + w),
+            ];
+            for n in neighbors.into_iter().flatten() {
+                if labels[n] == usize::MAX && mask.as_raw()[n] != 0 {
+                    labels[n] = next;
+                    stack.push(n);
+                }
+            }
+        }
+        next += 1;
+    }
+
+    labels
+}
+
+/// 多边形的有向面积，顺时针（屏幕方向）为正
+pub fn signed_area(points: &[(f32, f32)]) -> f32 {
+    let n = points.len();
+    (0..n)
+        .map(|i| {
+            let (x1, y1) = points[i];
+            let (x2, y2) = points[(i + 1) % n];
+            x1 * y2 - x2 * y1
+        })
+        .sum::<f32>() / 2.0
+}
+
+fn bounds(points: &[(f32, f32)]) -> (f32, f32, f32, f32) {
+    points.iter().fold(
+        (f32::MAX, f32::MAX, f32::MIN, f32::MIN),
+        |(min_x, min_y, max_x, max_y), &(x, y)| (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y)),
+    )
+}
+
+/// 射线法判断点是否位于多边形内部
+fn contains(points: &[(f32, f32)], point: (f32, f32)) -> bool {
+    let (px, py) = point;
+    let n = points.len();
+    let mut inside = false;
+    for i in 0..n {
+        let (x1, y1) = points[i];
+        let (x2, y2) = points[(i + 1) % n];
+        if (y1 > py) != (y2 > py) && px < x1 + (py - y1) / (y2 - y1) * (x2 - x1) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 按字符画构造掩码，`#` 为前景
+    fn mask(rows: &[&str]) -> GrayImage {
+        let (width, height) = (rows[0].len() as u32, rows.len() as u32);
+        GrayImage::from_fn(width, height, |x, y| {
+            image::Luma([if rows[y as usize].as_bytes()[x as usize] == b'#' { 255 } else { 0 }])
+        })
+    }
+
+    #[test]
+    fn ring_has_outer_contour_and_hole() {
+        let contours = trace_contours(&mask(&[
+            "#####",
+            "#####",
+            "##.##",
+            "#####",
+            "#####",
+        ]));
+        assert_eq!(contours.len(), 2);
+        let outer = contours.iter().position(|c| !c.is_hole).unwrap();
+        let hole = contours.iter().position(|c| c.is_hole).unwrap();
+
+        assert_eq!(contours[outer].points.len(), 4);
+        assert_eq!(signed_area(&contours[outer].points), 25.0);
+        assert_eq!(contours[outer].parent, None);
+
+        // 孔洞方向与外轮廓相反，非零环绕规则下同样镂空
+        assert_eq!(signed_area(&contours[hole].points), -1.0);
+        assert_eq!(bounds(&contours[hole].points), (2.0, 2.0, 3.0, 3.0));
+        assert_eq!(contours[hole].parent, Some(outer));
+    }
+
+    #[test]
+    fn island_inside_hole_is_parented_to_the_hole() {
+        let contours = trace_contours(&mask(&[
+            "#######",
+            "#.....#",
+            "#.###.#",
+            "#.#.#.#",
+            "#.###.#",
+            "#.....#",
+            "#######",
+        ]));
+        assert_eq!(contours.len(), 4);
+        let by_area = |area: f32| contours.iter().position(|c| signed_area(&c.points) == area).unwrap();
+        let (outer, hole, island, inner_hole) = (by_area(49.0), by_area(-25.0), by_area(9.0), by_area(-1.0));
+
+        assert_eq!(contours[outer].parent, None);
+        assert_eq!(contours[hole].parent, Some(outer));
+        assert!(!contours[island].is_hole);
+        assert_eq!(contours[island].parent, Some(hole));
+        assert_eq!(contours[inner_hole].parent, Some(island));
+    }
+
+    #[test]
+    fn one_pixel_wide_strips_are_closed_rectangles() {
+        let horizontal = trace_contours(&mask(&["#####"]));
+        assert_eq!(horizontal.len(), 1);
+        assert_eq!(horizontal[0].points.len(), 4);
+        assert_eq!(signed_area(&horizontal[0].points), 5.0);
+        assert_eq!(bounds(&horizontal[0].points), (0.0, 0.0, 5.0, 1.0));
+
+        let vertical = trace_contours(&mask(&["#", "#", "#"]));
+        assert_eq!(vertical.len(), 1);
+        assert_eq!(signed_area(&vertical[0].points), 3.0);
+    }
+
+    #[test]
+    fn diagonal_pixels_are_separate_regions() {
+        let contours = trace_contours(&mask(&[
+            "#.",
+            ".#",
+        ]));
+        assert_eq!(contours.len(), 2);
+        assert!(contours.iter().all(|c| !c.is_hole && c.parent.is_none() && signed_area(&c.points) == 1.0));
+    }
+
+    #[test]
+    fn every_pixel_edge_belongs_to_exactly_one_contour() {
+        let mask = mask(&[
+            "##..##",
+            "#.##.#",
+            ".####.",
+            "#.##.#",
+            "##..##",
+        ]);
+        let contours = trace_contours(&mask);
+        let foreground = mask.pixels().filter(|p| p[0] != 0).count() as f32;
+        let area: f32 = contours.iter().map(|c| signed_area(&c.points)).sum();
+        assert_eq!(area, foreground);
+    }
+}
@@ -1,9 +1,9 @@
 use wasm_bindgen::prelude::*;
-use image::DynamicImage;
-use anyhow::Result;
 
-mod core;
-mod utils;
+pub mod core;
+pub mod utils;
+
+use core::error::ConvertError;
 
 // 当wasm发生panic时使用console.error输出错误
 #[wasm_bindgen]
@@ -26,6 +26,12 @@ pub struct SvgConverter {
     last_result: Option<Vec<u8>>,
 }
 
+impl Default for SvgConverter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[wasm_bindgen]
 impl SvgConverter {
     /// 创建一个新的SVG转换器实例
@@ -37,52 +43,579 @@ impl SvgConverter {
     }
 
     /// 将SVG转换为PNG
-    /// 
+    ///
+    /// 位置参数与早期版本保持一致，新增的渲染选项只通过 `svg_to_png_with_options` 提供。
+    ///
     /// @param svg_data - SVG数据（字符串）
-    /// @param width - 输出宽度（可选，设为0使用原始宽度）
-    /// @param height - 输出高度（可选，设为0使用原始高度）
+    /// @param width - 输出宽度（设为0时按高度等比缩放，宽高都为0时使用原始尺寸）
+    /// @param height - 输出高度（设为0时按宽度等比缩放）
     /// @returns Base64编码的PNG数据
     #[wasm_bindgen]
     pub fn svg_to_png(&mut self, svg_data: &str, width: u32, height: u32) -> Result<String, JsValue> {
-        match core::svg2png::convert_svg_to_png(svg_data, width, height) {
+        let options = core::svg2png::RenderOptions {
+            width: Some(width),
+            height: Some(height),
+            ..Default::default()
+        };
+        self.render(svg_data, &options)
+    }
+
+    /// 使用渲染选项对象将SVG转换为图像
+    ///
+    /// @param svg_data - SVG数据（字符串）
+    /// @param options - 渲染选项（`RenderOptions` 对象）
+    /// @returns Base64编码的图像数据URL，MIME类型与输出格式一致
+    #[wasm_bindgen]
+    pub fn svg_to_png_with_options(&mut self, svg_data: &str, options: &WasmRenderOptions) -> Result<String, JsValue> {
+        self.render(svg_data, &options.inner)
+    }
+
+    /// 将SVG按多个缩放倍数渲染为PNG，只解析一次
+    ///
+    /// @param svg_data - SVG数据（字符串）
+    /// @param options - 渲染选项（`RenderOptions` 对象）
+    /// @param scales - 缩放倍数数组，如 `[1, 2, 3]`
+    /// @returns `{ scale, dataUrl }` 对象数组，`scale` 为实际缩放倍数
+    #[wasm_bindgen]
+    pub fn svg_to_png_scales(&mut self, svg_data: &str, options: &WasmRenderOptions, scales: Vec<f32>) -> Result<js_sys::Array, JsValue> {
+        let images = core::svg2png::convert_svg_to_png_scales(svg_data, &scales, &options.inner)
+            .map_err(js_error)?;
+        images_to_array(images, options.inner.format)
+    }
+
+    /// 将SVG渲染为多种尺寸并打包为ICO图标（如favicon.ico）
+    ///
+    /// @param svg_data - SVG数据（字符串）
+    /// @param options - 渲染选项（`RenderOptions` 对象，其中的宽高与缩放设置被忽略）
+    /// @param sizes - 图标尺寸数组（可选，默认 `[16, 32, 48, 64, 128, 256]`，每个尺寸不超过256）
+    /// @returns ICO文件数据
+    #[wasm_bindgen]
+    pub fn svg_to_ico(&mut self, svg_data: &str, options: &WasmRenderOptions, sizes: Option<Vec<u32>>) -> Result<Vec<u8>, JsValue> {
+        self.icon(svg_data, core::icon::IconFormat::Ico, &options.inner, sizes)
+    }
+
+    /// 将SVG渲染为多种尺寸并打包为ICNS图标
+    ///
+    /// @param svg_data - SVG数据（字符串）
+    /// @param options - 渲染选项（`RenderOptions` 对象，其中的宽高与缩放设置被忽略）
+    /// @param sizes - 图标尺寸数组（可选，默认 `[16, 32, 64, 128, 256]`，可选16、32、64、128、256、512、1024）
+    /// @returns ICNS文件数据
+    #[wasm_bindgen]
+    pub fn svg_to_icns(&mut self, svg_data: &str, options: &WasmRenderOptions, sizes: Option<Vec<u32>>) -> Result<Vec<u8>, JsValue> {
+        self.icon(svg_data, core::icon::IconFormat::Icns, &options.inner, sizes)
+    }
+
+    /// 将SVG中的文本转换为路径，返回不依赖字体的SVG
+    ///
+    /// @param svg_data - SVG数据（字符串）
+    /// @param options - 渲染选项（`RenderOptions` 对象，使用其中通过 `add_font` 添加的字体、资源与安全模式设置）
+    /// @returns 文本已转换为路径的SVG数据（字符串）
+    #[wasm_bindgen]
+    pub fn outline_text(&mut self, svg_data: &str, options: &WasmRenderOptions) -> Result<String, JsValue> {
+        let inner = &options.inner;
+        let svg = core::svg2png::convert_text_to_path(svg_data, &inner.fonts, &inner.resources, inner.limits.as_ref())
+            .map_err(js_error)?;
+        self.last_result = Some(svg.clone().into_bytes());
+        Ok(svg)
+    }
+
+    /// 列出SVG文本引用但已加载字体中找不到的字体族，这些文本将使用后备字体渲染
+    ///
+    /// @param svg_data - SVG数据（字符串）
+    /// @param options - 渲染选项（`RenderOptions` 对象，使用其中通过 `add_font` 添加的字体）
+    /// @returns 缺少的字体族名称数组
+    #[wasm_bindgen]
+    pub fn missing_fonts(&self, svg_data: &str, options: &WasmRenderOptions) -> Result<Vec<String>, JsValue> {
+        core::svg2png::missing_fonts(svg_data, &options.inner.fonts)
+            .map_err(js_error)
+    }
+
+    /// 列出SVG精灵图中的图标id（所有带id的 `<symbol>` 以及顶层带id的 `<g>`）
+    ///
+    /// @param svg_data - SVG数据（字符串）
+    /// @returns 图标id数组
+    #[wasm_bindgen]
+    pub fn sprite_ids(&self, svg_data: &str) -> Result<Vec<String>, JsValue> {
+        core::svg2png::sprite_ids(svg_data)
+            .map_err(js_error)
+    }
+
+    /// 将SVG精灵图中的每个图标分别渲染为PNG
+    ///
+    /// @param svg_data - SVG数据（字符串）
+    /// @param options - 渲染选项（`RenderOptions` 对象，其中的元素与区域设置被忽略）
+    /// @param padding - 每个图标包围盒四周的留白，单位为SVG用户单位（可选，默认0）
+    /// @param scales - 缩放倍数数组（可选，默认 `[1]`）
+    /// @returns `{ id, scale, dataUrl }` 对象数组，没有可渲染内容的图标被跳过
+    #[wasm_bindgen]
+    pub fn render_sprites(
+        &mut self,
+        svg_data: &str,
+        options: &WasmRenderOptions,
+        padding: Option<f32>,
+        scales: Option<Vec<f32>>,
+    ) -> Result<js_sys::Array, JsValue> {
+        let scales = scales.unwrap_or_else(|| vec![1.0]);
+        let sprites = core::svg2png::render_sprites(svg_data, padding.unwrap_or(0.0), &scales, &options.inner)
+            .map_err(js_error)?;
+        images_to_array(sprites, options.inner.format)
+    }
+
+    /// 将PNG转换为SVG（同样接受JPEG、GIF、BMP、WebP、TIFF和ICO，格式由数据内容识别）
+    ///
+    /// 位置参数与早期版本保持一致，新增的矢量化选项只通过 `png_to_svg_with_options` 提供。
+    ///
+    /// @param png_data_base64 - Base64编码的图像数据（需要包含MIME前缀）
+    /// @param simplify - 简化级别 (0-10, 0表示不简化, 10表示最大简化)
+    /// @returns SVG数据（字符串）
+    #[wasm_bindgen]
+    pub fn png_to_svg(&mut self, png_data_base64: &str, simplify: u8) -> Result<String, JsValue> {
+        let options = core::png2svg::TraceOptions::builder()
+            .simplify(simplify)
+            .build();
+        self.trace(png_data_base64, &options, None)
+    }
+
+    /// 使用矢量化选项对象将PNG转换为SVG
+    ///
+    /// @param png_data_base64 - Base64编码的PNG数据（需要包含MIME前缀）
+    /// @param options - 矢量化选项（`TraceOptions` 对象）
+    /// @returns SVG数据（字符串）
+    #[wasm_bindgen]
+    pub fn png_to_svg_with_options(&mut self, png_data_base64: &str, options: &WasmTraceOptions) -> Result<String, JsValue> {
+        self.trace(png_data_base64, &options.inner, None)
+    }
+
+    /// 将位图（PNG、JPEG、GIF、BMP、WebP、TIFF、ICO）转换为SVG，格式由数据内容识别
+    ///
+    /// @param data_base64 - Base64编码的图像数据（需要包含MIME前缀）
+    /// @param options - 矢量化选项（`TraceOptions` 对象）
+    /// @param frame - GIF动画的帧序号，从0开始（可选，默认第一帧）
+    /// @returns SVG数据（字符串）
+    #[wasm_bindgen]
+    pub fn raster_to_svg(&mut self, data_base64: &str, options: &WasmTraceOptions, frame: Option<usize>) -> Result<String, JsValue> {
+        self.trace(data_base64, &options.inner, frame)
+    }
+
+    /// 获取最后生成的文件作为字节数组
+    #[wasm_bindgen]
+    pub fn get_last_result(&self) -> Option<Box<[u8]>> {
+        self.last_result.clone().map(|data| data.into_boxed_slice())
+    }
+}
+
+impl SvgConverter {
+    /// 按选项渲染SVG并编码为数据URL
+    fn render(&mut self, svg_data: &str, options: &core::svg2png::RenderOptions) -> Result<String, JsValue> {
+        match core::svg2png::convert_svg_to_png(svg_data, options) {
             Ok(png_data) => {
                 self.last_result = Some(png_data.clone());
                 // 使用工具模块处理Base64编码
-                let data_url = utils::encode_to_base64_data_url(&png_data, "image/png");
+                let data_url = utils::encode_to_base64_data_url(&png_data, options.format.mime_type());
                 Ok(data_url)
             },
-            Err(e) => Err(JsValue::from_str(&format!("Error converting SVG to PNG: {}", e))),
+            Err(e) => Err(js_error(e)),
         }
     }
 
-    /// 将PNG转换为SVG
-    /// 
-    /// @param png_data_base64 - Base64编码的PNG数据（需要包含MIME前缀）
-    /// @param simplify - 简化级别 (0-10, 0表示不简化, 10表示最大简化)
-    /// @returns SVG数据（字符串）
-    #[wasm_bindgen]
-    pub fn png_to_svg(&mut self, png_data_base64: &str, simplify: u8) -> Result<String, JsValue> {
+    /// 按格式渲染并打包图标文件
+    fn icon(
+        &mut self,
+        svg_data: &str,
+        format: core::icon::IconFormat,
+        options: &core::svg2png::RenderOptions,
+        sizes: Option<Vec<u32>>,
+    ) -> Result<Vec<u8>, JsValue> {
+        let icon_data = core::icon::convert_svg_to_icon(svg_data, format, &sizes.unwrap_or_default(), options)
+            .map_err(js_error)?;
+        self.last_result = Some(icon_data.clone());
+        Ok(icon_data)
+    }
+
+    /// 解码Base64图像数据并按选项矢量化
+    fn trace(&mut self, png_data_base64: &str, options: &core::png2svg::TraceOptions, frame: Option<usize>) -> Result<String, JsValue> {
         // 从Base64解码PNG数据
         let png_data = utils::extract_base64_data(png_data_base64)
-            .map_err(|e| JsValue::from_str(&format!("Invalid base64 data: {}", e)))?;
+            .map_err(js_error)?;
         
         // 加载图像
-        let img = image::load_from_memory(&png_data)
-            .map_err(|e| JsValue::from_str(&format!("Failed to load image: {}", e)))?;
+        let img = core::raster::decode_raster(&png_data, frame)
+            .map_err(js_error)?;
         
         // 使用核心模块将PNG转换为SVG
-        match core::png2svg::convert_png_to_svg(&img, simplify) {
+        match core::png2svg::convert_png_to_svg(&img, options) {
             Ok(svg_data) => {
                 self.last_result = Some(svg_data.clone().into_bytes());
                 Ok(svg_data)
             },
-            Err(e) => Err(JsValue::from_str(&format!("Error converting PNG to SVG: {}", e))),
+            Err(e) => Err(js_error(e)),
         }
     }
+}
 
-    /// 获取最后生成的文件作为字节数组
-    #[wasm_bindgen]
-    pub fn get_last_result(&self) -> Option<Box<[u8]>> {
-        self.last_result.clone().map(|data| data.into_boxed_slice())
+/// PNG转SVG的矢量化选项
+#[wasm_bindgen(js_name = TraceOptions)]
+pub struct WasmTraceOptions {
+    inner: core::png2svg::TraceOptions,
+}
+
+#[wasm_bindgen(js_class = TraceOptions)]
+impl WasmTraceOptions {
+    /// 创建矢量化选项
+    ///
+    /// @param preset - 预设（可选，"logo"、"photo"、"lineart" 或 "pixel-art"，默认 "logo"）
+    #[wasm_bindgen(constructor)]
+    pub fn new(preset: Option<String>) -> Result<WasmTraceOptions, JsValue> {
+        let preset = match preset {
+            Some(preset) => preset.parse()
+                .map_err(js_error)?,
+            None => core::png2svg::TracePreset::default(),
+        };
+        Ok(WasmTraceOptions { inner: core::png2svg::TraceOptions::preset(preset) })
+    }
+
+    /// 简化级别 (0-10, 0表示不简化, 10表示最大简化)
+    pub fn set_simplify(&mut self, level: u8) {
+        self.inner.path.simplify = level;
+    }
+
+    /// 颜色量化算法（"median-cut"、"k-means" 或 "octree"）
+    pub fn set_quantizer(&mut self, quantizer: &str) -> Result<(), JsValue> {
+        self.inner.color.quantizer = quantizer.parse()
+            .map_err(js_error)?;
+        Ok(())
+    }
+
+    /// 调色板颜色数
+    pub fn set_colors(&mut self, colors: u32) {
+        self.inner.color.max_colors = colors as usize;
+    }
+
+    /// 颜色空间（"srgb"、"linear-rgb"、"lab" 或 "oklab"）
+    ///
+    /// 未调用 `set_delta_e` 时，lab使用CIEDE2000，其他空间使用CIE76；
+    /// 显式设置了 "ciede2000" 时只能使用lab，否则转换时抛出 `invalid_option` 错误。
+    pub fn set_color_space(&mut self, color_space: &str) -> Result<(), JsValue> {
+        self.inner.color.metric.space = color_space.parse()
+            .map_err(js_error)?;
+        Ok(())
+    }
+
+    /// 色差公式（"cie76" 或 "ciede2000"，后者只能与lab颜色空间一起使用）
+    pub fn set_delta_e(&mut self, delta_e: &str) -> Result<(), JsValue> {
+        self.inner.color.metric.delta_e = Some(delta_e.parse()
+            .map_err(js_error)?);
+        Ok(())
+    }
+
+    /// 像素分配方式（"nearest" 或 "threshold"）
+    pub fn set_assignment(&mut self, assignment: &str) -> Result<(), JsValue> {
+        self.inner.color.assignment = assignment.parse()
+            .map_err(js_error)?;
+        Ok(())
+    }
+
+    /// 最近色分配时的抖动算法（"none"、"floyd-steinberg" 或 "ordered"）
+    pub fn set_dither(&mut self, dither: &str) -> Result<(), JsValue> {
+        self.inner.color.dither = dither.parse()
+            .map_err(js_error)?;
+        Ok(())
+    }
+
+    /// 像素归入图层的最大色差（仅用于 "threshold" 分配方式）
+    pub fn set_threshold(&mut self, threshold: f32) {
+        self.inner.color.threshold = threshold;
+    }
+
+    /// 小于该面积（像素）的连通区域在追踪前被消除，0表示不过滤
+    pub fn set_min_area(&mut self, min_area: u32) {
+        self.inner.color.min_area = min_area;
     }
-} 
\ No newline at end of file
+
+    /// 是否将小区域并入主导邻色，否则直接删除
+    pub fn set_merge_small(&mut self, merge_small: bool) {
+        self.inner.color.merge_small = merge_small;
+    }
+
+    /// 不透明度低于该值的像素视为透明
+    pub fn set_alpha_threshold(&mut self, alpha_threshold: u8) {
+        self.inner.color.alpha_threshold = alpha_threshold;
+    }
+
+    /// 带孔洞路径的填充规则（"nonzero" 或 "evenodd"）
+    pub fn set_fill_rule(&mut self, fill_rule: &str) -> Result<(), JsValue> {
+        self.inner.path.fill_rule = fill_rule.parse()
+            .map_err(js_error)?;
+        Ok(())
+    }
+
+    /// 是否拟合贝塞尔曲线
+    pub fn set_curves(&mut self, curves: bool) {
+        self.inner.path.curves = curves;
+    }
+
+    /// 曲线拟合允许的最大误差（像素）
+    pub fn set_curve_tolerance(&mut self, curve_tolerance: f32) {
+        self.inner.path.curve_tolerance = curve_tolerance;
+    }
+
+    /// 方向改变超过该角度（度）的顶点保留为尖角
+    pub fn set_corner_angle(&mut self, corner_angle: f32) {
+        self.inner.path.corner_angle = corner_angle;
+    }
+
+    /// 轮廓简化算法（"rdp" 或 "visvalingam"）
+    pub fn set_simplify_method(&mut self, simplify_method: &str) -> Result<(), JsValue> {
+        self.inner.path.simplify_method = simplify_method.parse()
+            .map_err(js_error)?;
+        Ok(())
+    }
+
+    /// 简化容差（像素），设置后覆盖简化级别；传入 undefined 恢复使用简化级别
+    pub fn set_tolerance(&mut self, tolerance: Option<f32>) {
+        self.inner.path.simplify_tolerance = tolerance;
+    }
+}
+
+/// SVG转PNG的渲染选项
+#[wasm_bindgen(js_name = RenderOptions)]
+pub struct WasmRenderOptions {
+    inner: core::svg2png::RenderOptions,
+}
+
+impl Default for WasmRenderOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen(js_class = RenderOptions)]
+impl WasmRenderOptions {
+    /// 创建默认渲染选项（原始尺寸，96 DPI，contain 适配）
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        WasmRenderOptions { inner: core::svg2png::RenderOptions::default() }
+    }
+
+    /// 输出宽度（像素）；只设置宽度时高度按宽高比计算，传入 undefined 或0取消设置
+    pub fn set_width(&mut self, width: Option<u32>) {
+        self.inner.width = width;
+    }
+
+    /// 输出高度（像素）；只设置高度时宽度按宽高比计算，传入 undefined 或0取消设置
+    pub fn set_height(&mut self, height: Option<u32>) {
+        self.inner.height = height;
+    }
+
+    /// 缩放倍数（仅在未指定宽高时生效）
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.inner.zoom = zoom;
+    }
+
+    /// 输出分辨率DPI（仅在未指定宽高时生效）
+    pub fn set_dpi(&mut self, dpi: f32) {
+        self.inner.dpi = dpi;
+    }
+
+    /// 同时指定宽高时的适配方式（"stretch"、"contain" 或 "cover"）
+    pub fn set_fit(&mut self, fit: &str) -> Result<(), JsValue> {
+        self.inner.fit = fit.parse()
+            .map_err(js_error)?;
+        Ok(())
+    }
+
+    /// 背景（"transparent"、"checkerboard" 或CSS颜色，如 "white"、"#1e90ff"）
+    pub fn set_background(&mut self, background: &str) -> Result<(), JsValue> {
+        self.inner.background = background.parse()
+            .map_err(js_error)?;
+        Ok(())
+    }
+
+    /// 是否合成到白色上并去掉透明通道
+    pub fn set_flatten(&mut self, flatten: bool) {
+        self.inner.flatten = flatten;
+    }
+
+    /// 设置输出格式：png、jpeg、webp、tiff、bmp 或 qoi
+    pub fn set_format(&mut self, format: &str) -> Result<(), JsValue> {
+        self.inner.format = format.parse()
+            .map_err(js_error)?;
+        Ok(())
+    }
+
+    /// 设置JPEG质量（1-100）
+    pub fn set_quality(&mut self, quality: u8) {
+        self.inner.quality = quality;
+    }
+
+    /// 设置PNG颜色类型：truecolor、grayscale 或 palette
+    pub fn set_png_color(&mut self, color: &str) -> Result<(), JsValue> {
+        self.inner.png.color = color.parse()
+            .map_err(js_error)?;
+        Ok(())
+    }
+
+    /// 设置索引色PNG的最大颜色数（1-256）
+    pub fn set_palette_colors(&mut self, colors: u16) {
+        self.inner.png.palette_colors = colors;
+    }
+
+    /// 设置PNG每个通道的位深（8或16）
+    pub fn set_bit_depth(&mut self, bit_depth: u8) {
+        self.inner.png.bit_depth = bit_depth;
+    }
+
+    /// 设置PNG压缩级别：fast、default 或 best
+    pub fn set_compression(&mut self, compression: &str) -> Result<(), JsValue> {
+        self.inner.png.compression = compression.parse()
+            .map_err(js_error)?;
+        Ok(())
+    }
+
+    /// 是否在PNG中写入pHYs块，记录输出像素对应的DPI（未指定宽高时为缩放倍数×DPI，否则由实际缩放比例得出）
+    pub fn set_write_dpi(&mut self, write_dpi: bool) {
+        self.inner.png.write_dpi = write_dpi;
+    }
+
+    /// 添加一条PNG文本元数据
+    pub fn add_text(&mut self, keyword: String, text: String) {
+        self.inner.png.text.push((keyword, text));
+    }
+
+    /// 添加转换器版本与源文件名（可选）的PNG文本元数据
+    pub fn add_converter_text(&mut self, source: Option<String>) {
+        self.inner.png.text.extend(core::svg2png::converter_text(source.as_deref()));
+    }
+
+    /// 清除所有PNG文本元数据
+    pub fn clear_text(&mut self) {
+        self.inner.png.text.clear();
+    }
+
+    /// 添加渲染文本用的字体文件数据（TTF、OTF或TTC）
+    pub fn add_font(&mut self, data: Vec<u8>) {
+        self.inner.fonts.data.push(std::sync::Arc::new(data));
+    }
+
+    /// 移除所有通过 `add_font` 添加的字体
+    pub fn clear_fonts(&mut self) {
+        self.inner.fonts.data.clear();
+    }
+
+    /// 设置未指定font-family时使用的字体族
+    pub fn set_default_font_family(&mut self, family: String) {
+        self.inner.fonts.default_family = family;
+    }
+
+    /// 提供SVG中 `<image>` 引用的资源数据（PNG、JPEG、GIF、WebP、BMP、TIFF或SVG）
+    ///
+    /// @param href - 与SVG中 `href` 属性完全一致的字符串，如 `"images/logo.png"`
+    /// @param data - 资源文件数据
+    pub fn add_resource(&mut self, href: String, data: Vec<u8>) {
+        self.inner.resources.data.insert(href, std::sync::Arc::new(data));
+    }
+
+    /// 移除所有通过 `add_resource` 添加的资源
+    pub fn clear_resources(&mut self) {
+        self.inner.resources.data.clear();
+    }
+
+    /// 启用或关闭安全模式，处理用户上传的SVG时建议启用
+    ///
+    /// 安全模式按默认上限限制输入大小、输出像素数、元素数量、嵌套深度与引用展开后的节点数，
+    /// 超出时返回错误而不是耗尽内存；`add_resource` 添加的资源仍然可用。
+    pub fn set_secure(&mut self, enabled: bool) {
+        self.inner.limits = enabled.then(core::limits::Limits::default);
+    }
+
+    /// 调整安全模式的上限，省略的参数保持当前值；未启用安全模式时以默认上限启用
+    ///
+    /// @param max_input_bytes - SVG数据的最大字节数
+    /// @param max_pixels - 单张输出图像的最大像素数
+    /// @param max_elements - 最大元素数
+    /// @param max_depth - 最大嵌套深度
+    /// @param max_nodes - 引用展开后的最大节点数
+    pub fn set_limits(
+        &mut self,
+        max_input_bytes: Option<usize>,
+        max_pixels: Option<f64>,
+        max_elements: Option<usize>,
+        max_depth: Option<usize>,
+        max_nodes: Option<f64>,
+    ) {
+        let limits = self.inner.limits.get_or_insert_with(core::limits::Limits::default);
+        if let Some(value) = max_input_bytes {
+            limits.max_input_bytes = value;
+        }
+        if let Some(value) = max_pixels {
+            limits.max_pixels = value as u64;
+        }
+        if let Some(value) = max_elements {
+            limits.max_elements = value;
+        }
+        if let Some(value) = max_depth {
+            limits.max_depth = value;
+        }
+        if let Some(value) = max_nodes {
+            limits.max_nodes = value as u64;
+        }
+    }
+
+    /// 只渲染指定id的元素（包括 `<symbol>`），输出裁剪到其包围盒
+    ///
+    /// @param id - 元素id
+    /// @param padding - 包围盒四周的留白，单位为SVG用户单位（可选，默认0）
+    pub fn set_element(&mut self, id: &str, padding: Option<f32>) {
+        self.inner.crop = Some(core::svg2png::Crop::Element {
+            id: id.to_string(),
+            padding: padding.unwrap_or(0.0),
+        });
+    }
+
+    /// 只渲染viewBox坐标系中的矩形区域
+    pub fn set_area(&mut self, x: f32, y: f32, width: f32, height: f32) -> Result<(), JsValue> {
+        if width <= 0.0 || height <= 0.0 {
+            return Err(js_error(ConvertError::InvalidDimensions("区域的宽高必须大于0".to_string())));
+        }
+        self.inner.crop = Some(core::svg2png::Crop::Area(core::svg2png::Area { x, y, width, height }));
+        Ok(())
+    }
+
+    /// 取消 `set_element` 或 `set_area` 的设置，渲染整个SVG
+    pub fn clear_crop(&mut self) {
+        self.inner.crop = None;
+    }
+}
+
+/// 将转换错误转换为JavaScript的 `Error` 对象
+/// 
+/// 对象的 `name` 为 `"ConvertError"`，`code` 为稳定的错误码（如 `"parse_svg"`），
+/// 超出安全模式上限时 `limit` 为具体的上限类型（如 `"too_many_pixels"`）。
+fn js_error(error: ConvertError) -> JsValue {
+    let js_error = js_sys::Error::new(&error.to_string());
+    js_error.set_name("ConvertError");
+    let _ = js_sys::Reflect::set(&js_error, &"code".into(), &error.code().into());
+    if let ConvertError::LimitExceeded(limit) = &error {
+        let _ = js_sys::Reflect::set(&js_error, &"limit".into(), &limit.kind().into());
+    }
+    js_error.into()
+}
+
+/// 将批量渲染结果转换为 `{ id?, scale, dataUrl }` 对象数组
+fn images_to_array(
+    images: Vec<core::svg2png::RenderedImage>,
+    format: core::svg2png::OutputFormat,
+) -> Result<js_sys::Array, JsValue> {
+    let result = js_sys::Array::new();
+    for image in images {
+        let item = js_sys::Object::new();
+        if let Some(id) = image.id {
+            js_sys::Reflect::set(&item, &"id".into(), &id.into())?;
+        }
+        js_sys::Reflect::set(&item, &"scale".into(), &image.scale.into())?;
+        let data_url = utils::encode_to_base64_data_url(&image.data, format.mime_type());
+        js_sys::Reflect::set(&item, &"dataUrl".into(), &data_url.into())?;
+        result.push(&item);
+    }
+    Ok(result)
+}
@@ -1,9 +1,21 @@
-use std::error::Error;
+use std::process::ExitCode;
+use std::path::PathBuf;
 
 use clap::{Parser, Subcommand};
 
-mod core;
-mod utils;
+use svg_converter::core;
+use svg_converter::core::error::ConvertError;
+use svg_converter::core::icon::IconFormat;
+use svg_converter::core::color::{ColorSpace, DeltaE};
+use svg_converter::core::fonts::FontOptions;
+use svg_converter::core::limits::Limits;
+use svg_converter::core::resources::{ExternalPolicy, ResourceOptions};
+use svg_converter::core::png2svg::{Assignment, Dither, FillRule, TraceOptionsBuilder, TracePreset};
+use svg_converter::core::quantize::Quantizer;
+use svg_converter::core::simplify::SimplifyMethod;
+use svg_converter::core::svg2png::{
+    converter_text, Area, Background, Crop, FileReport, FitMode, OutputFormat, PngColor, PngCompression, PngOptions, RenderOptions,
+};
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about)]
@@ -12,48 +24,436 @@ struct Args {
     command: Commands,
 }
 
+/// SVG渲染的公共参数
+#[derive(clap::Args, Debug)]
+struct RenderArgs {
+    /// 缩放倍数（仅在未指定宽高时生效）
+    #[clap(long, default_value = "1.0")]
+    zoom: f32,
+    /// 输出分辨率DPI，SVG中1px按96 DPI换算（仅在未指定宽高时生效）
+    #[clap(long, default_value = "96")]
+    dpi: f32,
+    /// 同时指定宽高时的适配方式 (stretch, contain, cover)
+    #[clap(long, default_value = "contain")]
+    fit: FitMode,
+    /// 背景 (transparent, checkerboard 或CSS颜色，如 white、#1e90ff)
+    #[clap(long, default_value = "transparent")]
+    background: Background,
+    /// 合成到白色上并去掉透明通道，输出RGB图像
+    #[clap(long)]
+    flatten: bool,
+    /// 输出格式 (png, jpeg, webp, tiff, bmp, qoi)，默认按输出文件扩展名推断，无法推断时为png
+    #[clap(long)]
+    format: Option<OutputFormat>,
+    /// JPEG质量 (1-100)
+    #[clap(long, default_value = "90")]
+    quality: u8,
+    /// PNG颜色类型 (truecolor, grayscale, palette)
+    #[clap(long, default_value = "truecolor")]
+    png_color: PngColor,
+    /// 索引色PNG的最大颜色数 (1-256)
+    #[clap(long, default_value = "256")]
+    palette_colors: u16,
+    /// PNG每个通道的位深 (8, 16)
+    #[clap(long, default_value = "8")]
+    bit_depth: u8,
+    /// PNG压缩级别 (fast, default, best)
+    #[clap(long, default_value = "default")]
+    compression: PngCompression,
+    /// 在PNG中写入pHYs块，记录输出像素对应的DPI（未指定宽高时为缩放倍数×--dpi，否则由实际缩放比例得出）
+    #[clap(long)]
+    write_dpi: bool,
+    /// 在PNG中写入转换器版本与源文件名的文本元数据
+    #[clap(long)]
+    metadata: bool,
+    #[clap(flatten)]
+    fonts: FontArgs,
+    #[clap(flatten)]
+    resources: ResourceArgs,
+    /// 安全模式：限制输入大小、输出像素数、元素数量与嵌套深度，并禁止读取外部文件
+    #[clap(long)]
+    secure: bool,
+}
+
+/// 文本渲染的字体参数
+#[derive(clap::Args, Debug)]
+struct FontArgs {
+    /// 额外加载的字体文件或目录（可重复指定）
+    #[clap(long = "font-dir", value_name = "PATH")]
+    font_dirs: Vec<PathBuf>,
+    /// 不加载系统字体，只使用--font-dir指定的字体
+    #[clap(long)]
+    no_system_fonts: bool,
+    /// 未指定font-family时使用的字体族
+    #[clap(long, default_value = "sans-serif")]
+    default_font: String,
+}
+
+impl FontArgs {
+    fn to_options(&self) -> FontOptions {
+        FontOptions {
+            system_fonts: !self.no_system_fonts,
+            paths: self.font_dirs.clone(),
+            data: Vec::new(),
+            default_family: self.default_font.clone(),
+        }
+    }
+}
+
+/// `<image>` 引用的外部资源参数
+#[derive(clap::Args, Debug)]
+struct ResourceArgs {
+    /// 解析图像相对路径的目录，默认为输入文件所在目录
+    #[clap(long, value_name = "DIR")]
+    resources_dir: Option<PathBuf>,
+    /// 外部图像文件的访问策略 (allow: 任意文件, resources: 仅资源目录内, deny: 禁止)
+    #[clap(long, default_value = "resources")]
+    external: ExternalPolicy,
+}
+
+impl ResourceArgs {
+    fn to_options(&self) -> ResourceOptions {
+        ResourceOptions {
+            resources_dir: self.resources_dir.clone(),
+            policy: self.external,
+            ..Default::default()
+        }
+    }
+}
+
+/// 在标准错误输出中列出转换时找不到的字体族
+fn report_missing_fonts(report: &FileReport) {
+    for family in &report.missing_fonts {
+        eprintln!("警告：找不到字体 \"{}\"，相应文本已使用后备字体", family);
+    }
+}
+
+impl RenderArgs {
+    /// 转换为渲染选项（宽高为0时由渲染视为未指定）；未指定格式时按输出路径的扩展名推断
+    fn to_options(&self, width: Option<u32>, height: Option<u32>, input: &str, output: &str) -> RenderOptions {
+        let source = std::path::Path::new(input).file_name().and_then(|name| name.to_str());
+        RenderOptions {
+            width,
+            height,
+            zoom: self.zoom,
+            dpi: self.dpi,
+            fit: self.fit,
+            background: self.background,
+            flatten: self.flatten,
+            crop: None,
+            format: self.format
+                .or_else(|| OutputFormat::from_path(output))
+                .unwrap_or_default(),
+            quality: self.quality,
+            png: PngOptions {
+                color: self.png_color,
+                palette_colors: self.palette_colors,
+                bit_depth: self.bit_depth,
+                compression: self.compression,
+                write_dpi: self.write_dpi,
+                text: if self.metadata { converter_text(source) } else { Vec::new() },
+            },
+            fonts: self.fonts.to_options(),
+            resources: self.resources.to_options(),
+            limits: self.secure.then(Limits::default),
+        }
+    }
+}
+
 #[derive(Subcommand, Debug)]
 enum Commands {
-    /// 将SVG转换为PNG
+    /// 将SVG转换为PNG（或JPEG、WebP、TIFF、BMP、QOI）
     SvgToPng {
         /// 输入SVG文件路径
         input: String,
-        /// 输出PNG文件路径
+        /// 输出图像文件路径（使用--scales时为文件名模板），扩展名决定默认输出格式
         #[clap(value_name = "OUTPUT")]
         output: String,
-        /// 输出宽度（可选，默认使用SVG原始宽度）
-        #[clap(default_value = "0")]
-        width: u32,
-        /// 输出高度（可选，默认使用SVG原始高度）
-        #[clap(default_value = "0")]
-        height: u32,
+        /// 输出宽度（可选，省略或为0时按高度等比缩放，都省略时使用SVG原始尺寸）
+        width: Option<u32>,
+        /// 输出高度（可选，省略或为0时按宽度等比缩放）
+        height: Option<u32>,
+        #[clap(flatten)]
+        render: RenderArgs,
+        /// 只渲染指定id的元素（包括<symbol>），输出裁剪到其包围盒
+        #[clap(long, conflicts_with = "area")]
+        id: Option<String>,
+        /// 按id渲染时包围盒四周的留白（SVG用户单位）
+        #[clap(long, default_value = "0", requires = "id")]
+        padding: f32,
+        /// 只渲染viewBox坐标系中的矩形区域，格式: x,y,width,height
+        #[clap(long, allow_hyphen_values = true)]
+        area: Option<Area>,
+        /// 一次解析后按多个缩放倍数输出，如 1,2,3；OUTPUT中的{scale}替换为倍数，没有时在扩展名前插入@{scale}x
+        #[clap(long, value_delimiter = ',')]
+        scales: Option<Vec<f32>>,
     },
-    /// 将PNG转换为SVG
-    PngToSvg {
-        /// 输入PNG文件路径
+    /// 将SVG精灵图中的每个<symbol>和顶层<g id>分别导出为PNG
+    SvgSprites {
+        /// 输入SVG精灵图路径
+        input: String,
+        /// 输出目录
+        #[clap(value_name = "OUTPUT_DIR")]
+        output_dir: String,
+        /// 文件名模板，{id}替换为图标id，{scale}替换为缩放倍数
+        #[clap(long, default_value = "{id}@{scale}x.png")]
+        template: String,
+        /// 每个图标包围盒四周的留白（SVG用户单位）
+        #[clap(long, default_value = "0")]
+        padding: f32,
+        /// 每个图标的输出宽度（可选，只指定宽度时高度按宽高比计算）
+        #[clap(long)]
+        width: Option<u32>,
+        /// 每个图标的输出高度（可选，只指定高度时宽度按宽高比计算）
+        #[clap(long)]
+        height: Option<u32>,
+        /// 缩放倍数列表，如 1,2,3，每个图标按每个倍数各导出一次
+        #[clap(long, value_delimiter = ',', default_value = "1")]
+        scales: Vec<f32>,
+        #[clap(flatten)]
+        render: RenderArgs,
+    },
+    /// 将SVG渲染为多种尺寸并打包为ICO或ICNS图标
+    SvgToIco {
+        /// 输入SVG文件路径
+        input: String,
+        /// 输出图标文件路径（.ico 或 .icns）
+        #[clap(value_name = "OUTPUT")]
+        output: String,
+        /// 图标格式 (ico, icns)，默认按输出文件扩展名推断
+        #[clap(long)]
+        format: Option<IconFormat>,
+        /// 图标尺寸列表，ICO默认16,32,48,64,128,256，ICNS默认16,32,64,128,256
+        #[clap(long, value_delimiter = ',')]
+        sizes: Vec<u32>,
+        /// 宽高比不为1时的适配方式 (stretch, contain, cover)
+        #[clap(long, default_value = "contain")]
+        fit: FitMode,
+        /// 背景 (transparent, checkerboard 或CSS颜色，如 white、#1e90ff)
+        #[clap(long, default_value = "transparent")]
+        background: Background,
+        /// 合成到白色上并去掉透明通道
+        #[clap(long)]
+        flatten: bool,
+        #[clap(flatten)]
+        fonts: FontArgs,
+        #[clap(flatten)]
+        resources: ResourceArgs,
+        /// 安全模式，与svg-to-png相同
+        #[clap(long)]
+        secure: bool,
+    },
+    /// 将SVG中的文本转换为路径，输出不依赖字体的SVG
+    SvgOutlineText {
+        /// 输入SVG文件路径
+        input: String,
+        /// 输出SVG文件路径
+        #[clap(value_name = "OUTPUT")]
+        output: String,
+        #[clap(flatten)]
+        fonts: FontArgs,
+        #[clap(flatten)]
+        resources: ResourceArgs,
+        /// 安全模式，与svg-to-png相同
+        #[clap(long)]
+        secure: bool,
+    },
+    /// 将位图（PNG、JPEG、GIF、BMP、WebP、TIFF、ICO）转换为SVG
+    #[clap(alias = "png-to-svg")]
+    RasterToSvg {
+        /// 输入图像文件路径，格式由文件内容识别
         input: String,
         /// 输出SVG文件路径
         #[clap(value_name = "OUTPUT")]
         output: String,
-        /// 简化级别 (0-10, 0表示不简化, 10表示最大简化)
-        #[clap(default_value = "3")]
-        simplify: u8,
+        /// 简化级别 (0-10, 0表示不简化, 10表示最大简化)，默认3
+        simplify: Option<u8>,
+        /// GIF动画的帧序号（从0开始），默认第一帧
+        #[clap(long)]
+        frame: Option<usize>,
+        /// 选项预设 (logo, photo, lineart, pixel-art)，其余参数在预设的基础上覆盖
+        #[clap(long, default_value = "logo")]
+        preset: TracePreset,
+        /// 颜色量化算法 (median-cut, k-means, octree)，默认median-cut
+        #[clap(long)]
+        quantizer: Option<Quantizer>,
+        /// 调色板颜色数，默认8
+        #[clap(long)]
+        colors: Option<usize>,
+        /// 颜色空间 (srgb, linear-rgb, lab, oklab)，默认lab；未指定--delta-e时，lab使用ciede2000，其他空间使用cie76
+        #[clap(long)]
+        color_space: Option<ColorSpace>,
+        /// 色差公式 (cie76, ciede2000)，默认按颜色空间选择；ciede2000只能与lab一起使用
+        #[clap(long)]
+        delta_e: Option<DeltaE>,
+        /// 像素分配方式 (nearest, threshold)，默认nearest
+        #[clap(long)]
+        assignment: Option<Assignment>,
+        /// 最近色分配时的抖动算法 (none, floyd-steinberg, ordered)，默认none
+        #[clap(long)]
+        dither: Option<Dither>,
+        /// 像素归入图层的最大色差（仅用于threshold分配方式），默认20
+        #[clap(long)]
+        threshold: Option<f32>,
+        /// 小于该面积（像素）的连通区域在追踪前被消除，0表示不过滤，默认0（photo、lineart预设分别为16、8）
+        #[clap(long)]
+        min_area: Option<u32>,
+        /// 直接删除小区域，而不是并入主导邻色
+        #[clap(long)]
+        no_merge: bool,
+        /// 不透明度低于该值的像素视为透明，默认128
+        #[clap(long)]
+        alpha_threshold: Option<u8>,
+        /// 带孔洞路径的填充规则 (nonzero, evenodd)，默认evenodd
+        #[clap(long)]
+        fill_rule: Option<FillRule>,
+        /// 不进行曲线拟合，输出折线路径
+        #[clap(long)]
+        no_curves: bool,
+        /// 曲线拟合允许的最大误差（像素），默认1.0
+        #[clap(long)]
+        curve_tolerance: Option<f32>,
+        /// 方向改变超过该角度（度）的顶点保留为尖角，默认60
+        #[clap(long)]
+        corner_angle: Option<f32>,
+        /// 轮廓简化算法 (rdp, visvalingam)，默认rdp
+        #[clap(long)]
+        simplify_method: Option<SimplifyMethod>,
+        /// 简化容差（像素），设置后覆盖简化级别
+        #[clap(long)]
+        tolerance: Option<f32>,
     },
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
+/// 出错时在标准错误输出中给出错误码与消息，并以错误类型对应的退出码退出（见 `ConvertError::exit_code`）
+fn main() -> ExitCode {
     let args = Args::parse();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("错误[{}]: {}", error.code(), error);
+            ExitCode::from(error.exit_code())
+        }
+    }
+}
 
+fn run(args: &Args) -> Result<(), ConvertError> {
     match &args.command {
-        Commands::SvgToPng { input, output, width, height } => {
-            // 使用新的核心模块
-            core::svg2png::convert_svg_file_to_png_file(input, output, *width, *height)?;
-            println!("成功将SVG转换为PNG：{} -> {}", input, output);
+        Commands::SvgToPng { input, output, width, height, render, id, padding, area, scales } => {
+            let mut options = render.to_options(*width, *height, input, output);
+            options.crop = match (id, area) {
+                (Some(id), _) => Some(Crop::Element { id: id.clone(), padding: *padding }),
+                (None, Some(area)) => Some(Crop::Area(*area)),
+                (None, None) => None,
+            };
+            if let Some(scales) = scales {
+                let report = core::svg2png::convert_svg_file_to_png_files(input, output, scales, &options)?;
+                report_missing_fonts(&report);
+                for path in &report.written {
+                    println!("{}", path.display());
+                }
+                println!("成功将SVG转换为{}个PNG：{}", report.written.len(), input);
+            } else {
+                // 使用新的核心模块
+                let report = core::svg2png::convert_svg_file_to_png_file(input, output, &options)?;
+                report_missing_fonts(&report);
+                println!("成功将SVG转换为PNG：{} -> {}", input, output);
+            }
+        }
+        Commands::SvgSprites { input, output_dir, template, padding, width, height, scales, render } => {
+            let options = render.to_options(*width, *height, input, template);
+            let report = core::svg2png::export_sprite_file(input, output_dir, template, *padding, scales, &options)?;
+            report_missing_fonts(&report);
+            for id in &report.skipped {
+                eprintln!("警告：图标 \"{}\" 没有可渲染的内容，已跳过", id);
+            }
+            for path in &report.written {
+                println!("{}", path.display());
+            }
+            println!("成功从{}导出{}个图标到{}", input, report.written.len(), output_dir);
+        }
+        Commands::SvgToIco { input, output, format, sizes, fit, background, flatten, fonts, resources, secure } => {
+            let options = RenderOptions {
+                fit: *fit,
+                background: *background,
+                flatten: *flatten,
+                fonts: fonts.to_options(),
+                resources: resources.to_options(),
+                limits: secure.then(Limits::default),
+                ..Default::default()
+            };
+            let report = core::icon::convert_svg_file_to_icon_file(input, output, *format, sizes, &options)?;
+            report_missing_fonts(&report);
+            println!("成功将SVG转换为图标：{} -> {}", input, output);
+        }
+        Commands::SvgOutlineText { input, output, fonts, resources, secure } => {
+            let fonts = fonts.to_options();
+            let limits = secure.then(Limits::default);
+            let report = core::svg2png::convert_text_to_path_file(input, output, &fonts, &resources.to_options(), limits.as_ref())?;
+            report_missing_fonts(&report);
+            println!("成功将SVG中的文本转换为路径：{} -> {}", input, output);
         }
-        Commands::PngToSvg { input, output, simplify } => {
+        Commands::RasterToSvg {
+            input, output, simplify, frame, preset, quantizer, colors, color_space, delta_e,
+            assignment, dither, threshold, min_area, no_merge, alpha_threshold, fill_rule,
+            no_curves, curve_tolerance, corner_angle, simplify_method, tolerance,
+        } => {
+            // 在预设的基础上覆盖显式指定的参数
+            let mut builder = TraceOptionsBuilder::from_preset(*preset);
+            if let Some(simplify) = *simplify {
+                builder = builder.simplify(simplify);
+            }
+            if let Some(quantizer) = *quantizer {
+                builder = builder.quantizer(quantizer);
+            }
+            if let Some(colors) = *colors {
+                builder = builder.colors(colors);
+            }
+            if let Some(color_space) = *color_space {
+                builder = builder.color_space(color_space);
+            }
+            if let Some(delta_e) = *delta_e {
+                builder = builder.delta_e(delta_e);
+            }
+            if let Some(assignment) = *assignment {
+                builder = builder.assignment(assignment);
+            }
+            if let Some(dither) = *dither {
+                builder = builder.dither(dither);
+            }
+            if let Some(threshold) = *threshold {
+                builder = builder.threshold(threshold);
+            }
+            if let Some(min_area) = *min_area {
+                builder = builder.min_area(min_area);
+            }
+            if *no_merge {
+                builder = builder.merge_small(false);
+            }
+            if let Some(alpha_threshold) = *alpha_threshold {
+                builder = builder.alpha_threshold(alpha_threshold);
+            }
+            if let Some(fill_rule) = *fill_rule {
+                builder = builder.fill_rule(fill_rule);
+            }
+            if *no_curves {
+                builder = builder.curves(false);
+            }
+            if let Some(curve_tolerance) = *curve_tolerance {
+                builder = builder.curve_tolerance(curve_tolerance);
+            }
+            if let Some(corner_angle) = *corner_angle {
+                builder = builder.corner_angle(corner_angle);
+            }
+            if let Some(simplify_method) = *simplify_method {
+                builder = builder.simplify_method(simplify_method);
+            }
+            if let Some(tolerance) = *tolerance {
+                builder = builder.simplify_tolerance(tolerance);
+            }
             // 使用新的核心模块
-            core::png2svg::convert_png_file_to_svg_file(input, output, *simplify)?;
-            println!("成功将PNG转换为SVG：{} -> {}", input, output);
+            core::png2svg::convert_raster_file_to_svg_file(input, output, *frame, &builder.build())?;
+            println!("成功将图像转换为SVG：{} -> {}", input, output);
         }
     }
 
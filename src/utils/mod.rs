@@ -1,13 +1,15 @@
-use anyhow::{Result, anyhow};
 use base64::{Engine as _, engine::general_purpose};
 
+use crate::core::error::{ConvertError, Result};
+
 /// 从Base64编码字符串中提取实际数据部分
 pub fn extract_base64_data(data_url: &str) -> Result<Vec<u8>> {
     if let Some(pos) = data_url.find(";base64,") {
         let base64_data = &data_url[pos + 8..];
-        Ok(general_purpose::STANDARD.decode(base64_data)?)
+        general_purpose::STANDARD.decode(base64_data)
+            .map_err(|e| ConvertError::Decode(format!("Base64解码错误: {}", e)))
     } else {
-        Err(anyhow!("无法找到base64数据部分"))
+        Err(ConvertError::Decode("无法找到base64数据部分".to_string()))
     }
 }
 